@@ -0,0 +1,27 @@
+//! Stamps the build with the commit it was made from, for the run info panel
+//!
+//! `PANDEMIC_COMMIT` in the environment takes precedence, for builds outside a git checkout.
+
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=PANDEMIC_COMMIT");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs/heads");
+    println!("cargo:rerun-if-changed=.git/packed-refs");
+
+    let commit = std::env::var("PANDEMIC_COMMIT").ok().or_else(|| {
+        let output = Command::new("git")
+            .args(["rev-parse", "--short=12", "HEAD"])
+            .output()
+            .ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).trim().to_owned())
+    });
+    println!(
+        "cargo:rustc-env=PANDEMIC_COMMIT={}",
+        commit.as_deref().unwrap_or("unknown")
+    );
+}
@@ -0,0 +1,85 @@
+//! Command-line overrides of the starting scenario, so the native app can launch straight into it
+
+use crate::{Params, ensemble::RunOutcome};
+
+pub const USAGE: &str = "\
+Usage: pandemic [OPTIONS]
+
+Options:
+  --infected <N>          People infected at the start [default: 5]
+  --total <N>             Total people [default: 500]
+  --infection-prob <P>    Chance of infection, between 0 and 1
+  --death-prob <P>        Chance an infection ends in death, between 0 and 1
+  --infection-time <D>    Mean length of an infection, in days
+  --seed <N>              Seed for the random number generator [default: random]
+  --headless              Run once without a window and print a summary line
+  -h, --help              Print this help
+
+Headless summary, one line on stdout with these keys in this order:
+  seed=<N> total=<N> peak_infected=<N> peak_day=<D> deaths=<N> final_size=<F> duration_days=<D>
+where final_size is the fraction of people ever infected, and days have one decimal place.";
+
+/// The scenario the app starts in
+pub struct Startup {
+    pub infected: usize,
+    pub total: usize,
+    pub params: Params,
+    pub seed: Option<u64>,
+    /// Run once without a window instead of opening the app
+    pub headless: bool,
+}
+
+impl Startup {
+    /// Applies `args`, without the program name, on top of the default scenario
+    pub fn from_args(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut startup = Self {
+            infected: 5,
+            total: 500,
+            params: Params::default(),
+            seed: None,
+            headless: false,
+        };
+        while let Some(flag) = args.next() {
+            if flag == "--headless" {
+                startup.headless = true;
+                continue;
+            }
+            let value = args
+                .next()
+                .ok_or_else(|| format!("Missing a value for {flag}"))?;
+            let parse_error = |e: &dyn std::fmt::Display| format!("Invalid {flag} {value:?}: {e}");
+            let probability = || match value.parse::<f32>() {
+                Ok(p) if (0.0..=1.0).contains(&p) => Ok(p),
+                Ok(_) => Err(parse_error(&"must be between 0 and 1")),
+                Err(e) => Err(parse_error(&e)),
+            };
+            match flag.as_str() {
+                "--infected" => startup.infected = value.parse().map_err(|e| parse_error(&e))?,
+                "--total" => startup.total = value.parse().map_err(|e| parse_error(&e))?,
+                "--seed" => startup.seed = Some(value.parse().map_err(|e| parse_error(&e))?),
+                "--infection-prob" => startup.params.infection_prob = probability()?,
+                "--death-prob" => startup.params.death_prob = probability()?,
+                "--infection-time" => match value.parse::<f32>() {
+                    Ok(days) if days > 0.0 => startup.params.infection_time_s = days,
+                    Ok(_) => return Err(parse_error(&"must be positive")),
+                    Err(e) => return Err(parse_error(&e)),
+                },
+                _ => return Err(format!("Unknown option {flag}")),
+            }
+        }
+        Ok(startup)
+    }
+}
+
+/// The headless summary line documented in `USAGE`, in a fixed format for scripts to parse
+pub fn summary_line(seed: u64, total: usize, outcome: &RunOutcome) -> String {
+    format!(
+        "seed={seed} total={total} peak_infected={} peak_day={:.1} deaths={} final_size={:.3} \
+         duration_days={:.1}",
+        outcome.peak_infected,
+        outcome.peak_day,
+        outcome.deaths,
+        outcome.final_size,
+        outcome.duration_days
+    )
+}
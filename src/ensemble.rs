@@ -0,0 +1,440 @@
+//! Headless batch runs of the simulation, used to expose run-to-run variability
+
+use crate::{Pandemic, Params, rng};
+use std::fmt::Display;
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    thread::JoinHandle,
+};
+use web_time::Duration;
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+
+/// Simulated time advanced per headless step
+const HEADLESS_DT: Duration = Duration::from_millis(20);
+/// Runs still going after this long are cut off
+const MAX_DURATION: Duration = Duration::from_secs(365);
+/// Simulated time covered by each run of a reproducibility check
+const REPLAY_DURATION: Duration = Duration::from_secs(30);
+
+pub struct RunOutcome {
+    pub duration_days: f32,
+    pub deaths: usize,
+    /// Most people infected at once, and the day that first happened
+    pub peak_infected: usize,
+    pub peak_day: f32,
+    /// Fraction of the population ever infected
+    pub final_size: f32,
+}
+
+impl RunOutcome {
+    /// Runs a fresh simulation without rendering until the infection dies out
+    pub fn simulate(infected: usize, total: usize, params: &Params) -> Self {
+        let mut sim = Pandemic::with_params(infected, total, params.clone());
+        let (mut peak_infected, mut peak_day) = (sim.num_infected, 0.0);
+        while (sim.num_infected > 0 || sim.awaiting_seed) && sim.time_elapsed < MAX_DURATION {
+            sim.advance(HEADLESS_DT);
+            if sim.num_infected > peak_infected {
+                peak_infected = sim.num_infected;
+                peak_day = sim.time_elapsed.as_secs_f32();
+            }
+        }
+
+        Self {
+            duration_days: sim.time_elapsed.as_secs_f32(),
+            deaths: sim.num_dead,
+            peak_infected,
+            peak_day,
+            final_size: sim.num_ever_infected() as f32 / sim.population().max(1) as f32,
+        }
+    }
+}
+
+/// Runs the scenario twice from `seed`, returning the first recorded time at which their
+/// counts disagree
+///
+/// Seeded runs should replay exactly; any divergence means randomness is drawn from somewhere
+/// other than `rng`, or in an order that varies between runs, like a hash map's.
+pub fn first_divergence(
+    infected: usize,
+    total: usize,
+    params: &Params,
+    seed: u64,
+) -> Option<Duration> {
+    let run = || {
+        rng::with_seed(seed, || {
+            let mut sim = Pandemic::with_params(infected, total, params.clone());
+            while sim.time_elapsed < REPLAY_DURATION {
+                sim.advance(HEADLESS_DT);
+            }
+            sim.stats
+        })
+    };
+
+    let (first, second) = (run(), run());
+    let shorter = first.len().min(second.len());
+    first
+        .iter()
+        .zip(&second)
+        .find(|(a, b)| a != b)
+        .map(|(a, _)| a.time)
+        // A run that recorded more diverged where the other stopped
+        .or_else(|| {
+            first
+                .get(shorter)
+                .or(second.get(shorter))
+                .map(|stat| stat.time)
+        })
+}
+
+pub struct EnsembleResults {
+    pub runs: Vec<RunOutcome>,
+}
+
+impl EnsembleResults {
+    pub fn mean(&self, sample: impl Fn(&RunOutcome) -> f32) -> f32 {
+        self.runs.iter().map(sample).sum::<f32>() / self.runs.len().max(1) as f32
+    }
+}
+
+/// Ensembles running without blocking the UI: spread across worker threads natively, and a
+/// few runs at a time each frame on the web
+pub struct EnsembleJob {
+    /// Runs of each scenario
+    runs: usize,
+    scenarios: usize,
+    /// Each finished run, numbered scenario by scenario
+    #[cfg(not(target_arch = "wasm32"))]
+    finished: Arc<Mutex<Vec<(usize, RunOutcome)>>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    cancelled: Arc<AtomicBool>,
+    #[cfg(not(target_arch = "wasm32"))]
+    workers: Vec<JoinHandle<()>>,
+    #[cfg(target_arch = "wasm32")]
+    finished: Vec<(usize, RunOutcome)>,
+    #[cfg(target_arch = "wasm32")]
+    scenario: (usize, usize, Vec<Params>),
+}
+
+impl EnsembleJob {
+    /// Wall-clock time the web spends on runs each frame
+    #[cfg(target_arch = "wasm32")]
+    const FRAME_BUDGET: Duration = Duration::from_millis(30);
+
+    /// Starts `runs` runs of the scenario across `threads` worker threads
+    pub fn start(
+        runs: usize,
+        threads: usize,
+        infected: usize,
+        total: usize,
+        params: &Params,
+    ) -> Self {
+        Self::start_each(runs, threads, infected, total, vec![params.clone()])
+    }
+
+    /// Starts `runs` runs of the scenario with each of `scenarios`, across `threads` worker
+    /// threads
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn start_each(
+        runs: usize,
+        threads: usize,
+        infected: usize,
+        total: usize,
+        scenarios: Vec<Params>,
+    ) -> Self {
+        let count = runs * scenarios.len();
+        let finished = Arc::new(Mutex::new(Vec::with_capacity(count)));
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let claimed = Arc::new(AtomicUsize::new(0));
+        let shared = Arc::new(scenarios);
+        let workers = (0..threads.clamp(1, count.max(1)))
+            .map(|_| {
+                let (finished, cancelled, claimed, scenarios) = (
+                    finished.clone(),
+                    cancelled.clone(),
+                    claimed.clone(),
+                    shared.clone(),
+                );
+                std::thread::spawn(move || {
+                    while !cancelled.load(Ordering::Relaxed) {
+                        let i = claimed.fetch_add(1, Ordering::Relaxed);
+                        if i >= count {
+                            break;
+                        }
+                        let run = rng::aside(|| {
+                            RunOutcome::simulate(infected, total, &scenarios[i / runs])
+                        });
+                        finished.lock().unwrap().push((i, run));
+                    }
+                })
+            })
+            .collect();
+        Self {
+            runs,
+            scenarios: shared.len(),
+            finished,
+            cancelled,
+            workers,
+        }
+    }
+
+    /// Starts `runs` runs of the scenario with each of `scenarios`, made a few at a time by
+    /// `poll` on the one thread the web has
+    #[cfg(target_arch = "wasm32")]
+    pub fn start_each(
+        runs: usize,
+        _threads: usize,
+        infected: usize,
+        total: usize,
+        scenarios: Vec<Params>,
+    ) -> Self {
+        Self {
+            runs,
+            scenarios: scenarios.len(),
+            finished: Vec::with_capacity(runs * scenarios.len()),
+            scenario: (infected, total, scenarios),
+        }
+    }
+
+    /// Runs finished so far, out of all of them
+    pub fn progress(&self) -> (usize, usize) {
+        #[cfg(not(target_arch = "wasm32"))]
+        let finished = self.finished.lock().unwrap().len();
+        #[cfg(target_arch = "wasm32")]
+        let finished = self.finished.len();
+        (finished, self.runs * self.scenarios)
+    }
+
+    /// The results of each scenario, once every run has finished
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn poll(&mut self) -> Option<Vec<EnsembleResults>> {
+        let (finished, count) = self.progress();
+        if finished < count {
+            return None;
+        }
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+        let finished = std::mem::take(&mut *self.finished.lock().unwrap());
+        Some(self.by_scenario(finished))
+    }
+
+    /// Makes runs for up to `FRAME_BUDGET`, then returns the results of each scenario if every
+    /// run has finished
+    #[cfg(target_arch = "wasm32")]
+    pub fn poll(&mut self) -> Option<Vec<EnsembleResults>> {
+        let started = Instant::now();
+        let (_, count) = self.progress();
+        let (infected, total, scenarios) = &self.scenario;
+        while self.finished.len() < count && started.elapsed() < Self::FRAME_BUDGET {
+            let i = self.finished.len();
+            // On the live run's thread, so kept off its generator, as on the workers
+            let run =
+                rng::aside(|| RunOutcome::simulate(*infected, *total, &scenarios[i / self.runs]));
+            self.finished.push((i, run));
+        }
+        (self.finished.len() == count).then(|| {
+            let finished = std::mem::take(&mut self.finished);
+            self.by_scenario(finished)
+        })
+    }
+
+    /// Sorts numbered runs into their scenarios
+    fn by_scenario(&self, mut finished: Vec<(usize, RunOutcome)>) -> Vec<EnsembleResults> {
+        finished.sort_unstable_by_key(|&(i, _)| i);
+        let mut results: Vec<EnsembleResults> = (0..self.scenarios)
+            .map(|_| EnsembleResults {
+                runs: Vec::with_capacity(self.runs),
+            })
+            .collect();
+        for (i, run) in finished {
+            results[i / self.runs].runs.push(run);
+        }
+        results
+    }
+}
+
+/// Cancelled on drop: the workers stop after their current run
+#[cfg(not(target_arch = "wasm32"))]
+impl Drop for EnsembleJob {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+/// A run outcome that sensitivity analysis measures
+#[derive(Clone, Copy, PartialEq)]
+pub enum Measure {
+    Deaths,
+    Duration,
+}
+impl Measure {
+    pub const ALL: [Self; 2] = [Self::Deaths, Self::Duration];
+
+    pub fn of(self, run: &RunOutcome) -> f32 {
+        match self {
+            Self::Deaths => run.deaths as f32,
+            Self::Duration => run.duration_days,
+        }
+    }
+}
+impl Display for Measure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Deaths => "deaths",
+                Self::Duration => "days until extinction",
+            }
+        )
+    }
+}
+
+/// Mean outcome with one parameter scaled down and up
+pub struct SensitivityBar {
+    pub param: &'static str,
+    pub low: f32,
+    pub high: f32,
+}
+
+type ParamAccess = fn(&mut Params) -> &mut f32;
+
+/// How much perturbing each parameter moves the mean of a `Measure`
+pub struct Sensitivity {
+    pub measure: Measure,
+    /// Fraction each parameter was scaled down and up by
+    pub perturbation: f32,
+    pub baseline: f32,
+    /// Sorted by decreasing swing
+    pub bars: Vec<SensitivityBar>,
+}
+
+impl Sensitivity {
+    /// Parameters that are perturbed, with accessors and upper bounds
+    const PARAMS: [(&str, ParamAccess, f32); 5] = [
+        ("infection prob.", |p| &mut p.infection_prob, 1.0),
+        ("infection time", |p| &mut p.infection_time_s, f32::INFINITY),
+        ("death prob.", |p| &mut p.death_prob, 1.0),
+        ("cell size", |p| &mut p.cell_size, f32::INFINITY),
+        ("infected speed", |p| &mut p.infected_speed, f32::INFINITY),
+    ];
+
+    /// The baseline `params`, then each parameter scaled by `1 - perturbation` and by
+    /// `1 + perturbation` in turn
+    fn scenarios(params: &Params, perturbation: f32) -> Vec<Params> {
+        let scaled = |access: ParamAccess, max: f32, factor: f32| {
+            let mut params = params.clone();
+            let value = access(&mut params);
+            *value = (*value * factor).min(max);
+            params
+        };
+        std::iter::once(params.clone())
+            .chain(Self::PARAMS.into_iter().flat_map(|(_, access, max)| {
+                [
+                    scaled(access, max, 1.0 - perturbation),
+                    scaled(access, max, 1.0 + perturbation),
+                ]
+            }))
+            .collect()
+    }
+
+    /// Reads the analysis off an ensemble of each of `Self::scenarios`, in order
+    fn from_ensembles(measure: Measure, perturbation: f32, ensembles: &[EnsembleResults]) -> Self {
+        let mean = |i: usize| ensembles[i].mean(|run| measure.of(run));
+        let mut bars: Vec<SensitivityBar> = Self::PARAMS
+            .into_iter()
+            .enumerate()
+            .map(|(i, (param, _, _))| SensitivityBar {
+                param,
+                low: mean(1 + 2 * i),
+                high: mean(2 + 2 * i),
+            })
+            .collect();
+        bars.sort_by(|a, b| (b.high - b.low).abs().total_cmp(&(a.high - a.low).abs()));
+
+        Self {
+            measure,
+            perturbation,
+            baseline: mean(0),
+            bars,
+        }
+    }
+}
+
+/// A sensitivity analysis running as an `EnsembleJob`
+pub struct SensitivityJob {
+    job: EnsembleJob,
+    measure: Measure,
+    perturbation: f32,
+}
+
+impl SensitivityJob {
+    /// Starts ensembles of `runs` at the baseline `params` and at each parameter scaled by
+    /// `1 ± perturbation`, across `threads` worker threads
+    pub fn start(
+        runs: usize,
+        threads: usize,
+        infected: usize,
+        total: usize,
+        params: &Params,
+        measure: Measure,
+        perturbation: f32,
+    ) -> Self {
+        let scenarios = Sensitivity::scenarios(params, perturbation);
+        Self {
+            job: EnsembleJob::start_each(runs, threads, infected, total, scenarios),
+            measure,
+            perturbation,
+        }
+    }
+
+    /// Runs finished so far, out of all of them
+    pub fn progress(&self) -> (usize, usize) {
+        self.job.progress()
+    }
+
+    /// The analysis, once every run has finished
+    pub fn poll(&mut self) -> Option<Sensitivity> {
+        let ensembles = self.job.poll()?;
+        Some(Sensitivity::from_ensembles(
+            self.measure,
+            self.perturbation,
+            &ensembles,
+        ))
+    }
+}
+
+pub struct Histogram {
+    pub min: f32,
+    pub max: f32,
+    pub counts: Vec<usize>,
+}
+
+impl Histogram {
+    /// Buckets `samples` into `bins` equal-width bins spanning their range
+    pub fn new(samples: &[f32], bins: usize) -> Self {
+        let mut counts = vec![0; bins];
+        if samples.is_empty() {
+            return Self {
+                min: 0.0,
+                max: 0.0,
+                counts,
+            };
+        }
+
+        let min = samples.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = samples.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        let bin_width = (max - min).max(f32::EPSILON) / bins as f32;
+        for sample in samples {
+            let bin = ((sample - min) / bin_width) as usize;
+            counts[bin.min(bins - 1)] += 1;
+        }
+
+        Self { min, max, counts }
+    }
+}
@@ -0,0 +1,690 @@
+//! Exporting simulation data to files
+
+use crate::{InfectionState, PandemicSnapshot, Params, X_MAX_FLOAT, Y_MAX_FLOAT};
+use egui::{
+    Color32, Pos2,
+    ahash::{HashMap, HashMapExt},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{collections::BTreeMap, fmt::Write};
+use web_time::Duration;
+
+/// Everything needed to recreate a scenario
+#[derive(Serialize, Deserialize)]
+pub struct Config {
+    pub title: String,
+    pub notes: String,
+    pub init_infected: usize,
+    pub total: usize,
+    pub params: Params,
+}
+
+impl Config {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Config should always serialize")
+    }
+
+    pub fn from_json(json: &[u8]) -> Result<Self, String> {
+        serde_json::from_slice(json).map_err(|e| e.to_string())
+    }
+
+    /// Every setting as its dotted path and serialized value, sorted by path
+    fn fields(&self) -> BTreeMap<String, String> {
+        fn flatten(path: String, value: Value, fields: &mut BTreeMap<String, String>) {
+            match value {
+                Value::Object(map) => {
+                    for (key, value) in map {
+                        let path = if path.is_empty() {
+                            key
+                        } else {
+                            format!("{path}.{key}")
+                        };
+                        flatten(path, value, fields);
+                    }
+                }
+                value => {
+                    fields.insert(path, value.to_string());
+                }
+            }
+        }
+
+        let mut fields = BTreeMap::new();
+        let value = serde_json::to_value(self).expect("Config should always serialize");
+        flatten(String::new(), value, &mut fields);
+        fields
+    }
+
+    /// FNV-1a hash of the population and parameters, ignoring title and notes, so two runs of
+    /// the same scenario can be matched up
+    pub fn params_hash(&self) -> u64 {
+        let scenario = serde_json::to_string(&(self.init_infected, self.total, &self.params))
+            .expect("Config should always serialize");
+        scenario.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        })
+    }
+}
+
+/// One setting's value in each of two configs, either missing if the config lacks it
+pub struct FieldDiff {
+    pub path: String,
+    pub a: Option<String>,
+    pub b: Option<String>,
+}
+
+impl FieldDiff {
+    pub fn differs(&self) -> bool {
+        self.a != self.b
+    }
+}
+
+/// Every setting of `a` and `b` side by side, sorted by path
+pub fn config_diff(a: &Config, b: &Config) -> Vec<FieldDiff> {
+    let (mut a, mut b) = (a.fields(), b.fields());
+    let mut paths: Vec<String> = a.keys().chain(b.keys()).cloned().collect();
+    paths.sort();
+    paths.dedup();
+    paths
+        .into_iter()
+        .map(|path| FieldDiff {
+            a: a.remove(&path),
+            b: b.remove(&path),
+            path,
+        })
+        .collect()
+}
+
+/// `#`-prefixed CSV comment lines carrying the scenario title and notes
+fn comment_header(title: &str, notes: &str) -> String {
+    let mut header = String::new();
+    if !title.is_empty() {
+        let _ = writeln!(header, "# {title}");
+    }
+    for line in notes.lines() {
+        let _ = writeln!(header, "# {line}");
+    }
+    header
+}
+
+/// Samples `stats` every `interval` of simulated time as CSV, one row per sample
+///
+/// Each row carries the counts of the snapshot nearest to its sample time.
+pub fn time_series_csv(
+    stats: &[PandemicSnapshot],
+    interval: Duration,
+    title: &str,
+    notes: &str,
+) -> String {
+    let mut csv = comment_header(title, notes);
+    csv.push_str("time_days,healthy,infected,recovered,dead,cumulative_infected,reinfections\n");
+    for (time, stat) in sample_every(stats, interval) {
+        let _ = writeln!(
+            csv,
+            "{},{},{},{},{},{},{}",
+            time.as_secs_f32(),
+            stat.num_healthy,
+            stat.num_infected,
+            stat.num_recovered,
+            stat.num_dead,
+            stat.cumulative_infected,
+            stat.reinfections
+        );
+    }
+    csv
+}
+
+/// Reads real case counts as `(day, cases)` from CSV rows of `day,cases`, or of `cases` alone
+/// for consecutive days
+///
+/// Rows starting with `#` and a header row are skipped. A first column that isn't a number,
+/// like a date, is taken as the row's place in the sequence of days.
+pub fn parse_reference_csv(csv: &str) -> Result<Vec<(f32, f32)>, String> {
+    let mut days = Vec::new();
+    let mut header_skipped = false;
+    for (number, line) in csv.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let columns: Vec<&str> = line.split(',').map(str::trim).collect();
+        let (day, cases) = match columns[..] {
+            [cases] => (None, cases),
+            [day, cases, ..] => (day.parse::<f32>().ok(), cases),
+            [] => unreachable!("split always yields a column"),
+        };
+        match cases.parse::<f32>() {
+            Ok(cases) if cases >= 0.0 => {
+                days.push((day.unwrap_or(days.len() as f32), cases));
+            }
+            Ok(_) => return Err(format!("line {}: negative case count", number + 1)),
+            // Only the first row may be a header
+            Err(_) if days.is_empty() && !header_skipped => header_skipped = true,
+            Err(_) => {
+                return Err(format!(
+                    "line {}: case count {cases:?} isn't a number",
+                    number + 1
+                ));
+            }
+        }
+    }
+    if days.is_empty() {
+        Err("no case counts found".to_owned())
+    } else {
+        Ok(days)
+    }
+}
+
+/// How an infection ended
+#[derive(Clone, Copy)]
+pub enum Outcome {
+    Recovered,
+    Died,
+}
+
+/// A single infection event, from infection to its outcome
+#[derive(Clone)]
+struct InfectionRecord {
+    person_id: usize,
+    infected: Duration,
+    ended: Option<(Duration, Outcome)>,
+}
+
+/// Every infection event so far, for export as an epidemiological line list
+#[derive(Clone)]
+pub struct LineList {
+    records: Vec<InfectionRecord>,
+    /// Index into `records` of each currently infected person's open infection, by id
+    open: HashMap<usize, usize>,
+}
+
+impl LineList {
+    /// A line list holding the seed `infected`, by id, infected at time zero
+    pub fn seeded(infected: impl IntoIterator<Item = usize>) -> Self {
+        let mut list = Self {
+            records: Vec::new(),
+            open: HashMap::new(),
+        };
+        for id in infected {
+            list.infected(id, Duration::ZERO);
+        }
+        list
+    }
+
+    pub fn infected(&mut self, person_id: usize, time: Duration) {
+        self.open.insert(person_id, self.records.len());
+        self.records.push(InfectionRecord {
+            person_id,
+            infected: time,
+            ended: None,
+        });
+    }
+
+    pub fn ended(&mut self, person_id: usize, time: Duration, outcome: Outcome) {
+        if let Some(index) = self.open.remove(&person_id) {
+            self.records[index].ended = Some((time, outcome));
+        }
+    }
+
+    /// One CSV row per infection; ongoing infections have an empty outcome day
+    pub fn to_csv(&self, title: &str, notes: &str) -> String {
+        let mut csv = comment_header(title, notes);
+        csv.push_str("person_id,infection_day,outcome_day,outcome\n");
+        for record in &self.records {
+            let (day, outcome) = match record.ended {
+                Some((time, Outcome::Recovered)) => (time.as_secs_f32().to_string(), "recovered"),
+                Some((time, Outcome::Died)) => (time.as_secs_f32().to_string(), "died"),
+                None => (String::new(), "ongoing"),
+            };
+            let _ = writeln!(
+                csv,
+                "{},{},{day},{outcome}",
+                record.person_id,
+                record.infected.as_secs_f32()
+            );
+        }
+        csv
+    }
+}
+
+/// Where one tagged person was at one moment, and in what state
+#[derive(Clone)]
+struct TrajectoryPoint {
+    person_id: usize,
+    time: Duration,
+    pos: Pos2,
+    state: &'static str,
+}
+
+/// The paths of the people tagged for tracking, sampled as stats are recorded
+#[derive(Clone, Default)]
+pub struct Trajectories {
+    points: Vec<TrajectoryPoint>,
+}
+
+impl Trajectories {
+    pub fn record(&mut self, person_id: usize, time: Duration, pos: Pos2, state: &'static str) {
+        self.points.push(TrajectoryPoint {
+            person_id,
+            time,
+            pos,
+            state,
+        });
+    }
+
+    /// Drops everything sampled after `time`
+    pub fn truncate_after(&mut self, time: Duration) {
+        self.points.retain(|point| point.time <= time);
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    /// One CSV row per sample, in the order they were taken
+    pub fn to_csv(&self, title: &str, notes: &str) -> String {
+        let mut csv = comment_header(title, notes);
+        csv.push_str("person_id,time,x,y,state\n");
+        for point in &self.points {
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{}",
+                point.person_id,
+                point.time.as_secs_f32(),
+                point.pos.x,
+                point.pos.y,
+                point.state
+            );
+        }
+        csv
+    }
+}
+
+/// Where everyone was at one moment of a run, and in what state
+pub struct FilmFrame {
+    pub time: Duration,
+    pub people: Vec<(Pos2, InfectionState)>,
+}
+
+/// Grid snapshots taken at regular intervals during a run, for figures and animations
+#[derive(Default)]
+pub struct Filmstrip {
+    frames: Vec<FilmFrame>,
+}
+
+impl Filmstrip {
+    /// Most frames kept; later ones are not taken
+    pub const MAX_FRAMES: usize = 24;
+    /// Pixels per grid unit in the exported image
+    const SCALE: usize = 3;
+    /// Frames per row of the exported image, and the pixels between them
+    const COLUMNS: usize = 6;
+    const GAP: usize = 4;
+
+    pub fn frames(&self) -> &[FilmFrame] {
+        &self.frames
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.frames.len() >= Self::MAX_FRAMES
+    }
+
+    pub fn capture(&mut self, time: Duration, people: Vec<(Pos2, InfectionState)>) {
+        if !self.is_full() {
+            self.frames.push(FilmFrame { time, people });
+        }
+    }
+
+    /// Drops every frame taken after `time`
+    pub fn truncate_after(&mut self, time: Duration) {
+        self.frames.retain(|frame| frame.time <= time);
+    }
+
+    /// Every frame as an RGB PNG, left to right and then top to bottom and separated by grey,
+    /// with people drawn as small squares of their state's `color` on `background`
+    pub fn to_png(
+        &self,
+        background: Color32,
+        color: impl Fn(InfectionState) -> Color32,
+    ) -> Vec<u8> {
+        let frame_width = X_MAX_FLOAT as usize * Self::SCALE;
+        let frame_height = Y_MAX_FLOAT as usize * Self::SCALE;
+        let columns = self.frames.len().clamp(1, Self::COLUMNS);
+        let rows = self.frames.len().div_ceil(Self::COLUMNS).max(1);
+        let width = columns * (frame_width + Self::GAP) - Self::GAP;
+        let height = rows * (frame_height + Self::GAP) - Self::GAP;
+
+        let mut pixels = vec![128; 3 * width * height];
+        let mut paint = |x: usize, y: usize, color: Color32| {
+            let at = 3 * (y * width + x);
+            pixels[at..at + 3].copy_from_slice(&[color.r(), color.g(), color.b()]);
+        };
+        for (i, frame) in self.frames.iter().enumerate() {
+            let left = i % Self::COLUMNS * (frame_width + Self::GAP);
+            let top = i / Self::COLUMNS * (frame_height + Self::GAP);
+            for y in 0..frame_height {
+                for x in 0..frame_width {
+                    paint(left + x, top + y, background);
+                }
+            }
+            for &(pos, state) in &frame.people {
+                let x = (pos.x * Self::SCALE as f32) as usize;
+                let y = (pos.y * Self::SCALE as f32) as usize;
+                let rgb = color(state);
+                for y in y.saturating_sub(1)..(y + 2).min(frame_height) {
+                    for x in x.saturating_sub(1)..(x + 2).min(frame_width) {
+                        paint(left + x, top + y, rgb);
+                    }
+                }
+            }
+        }
+        encode_png(width, height, RGB, &pixels)
+    }
+}
+
+/// When infection first reached each contact cell as a grayscale PNG, one pixel per cell
+///
+/// The earliest cells are white, shading to near black for the latest; cells never reached
+/// are black.
+pub fn arrival_map_png(arrivals: &HashMap<(i32, i32), Duration>, cell_size: f32) -> Vec<u8> {
+    let width = (X_MAX_FLOAT / cell_size).ceil() as usize;
+    let height = (Y_MAX_FLOAT / cell_size).ceil() as usize;
+    let first = arrivals.values().min().copied().unwrap_or_default();
+    let last = arrivals.values().max().copied().unwrap_or_default();
+    let span = (last - first).as_secs_f32().max(f32::EPSILON);
+
+    let mut pixels = vec![0; width * height];
+    for (&(x, y), &time) in arrivals {
+        if (0..width as i32).contains(&x) && (0..height as i32).contains(&y) {
+            let lateness = (time - first).as_secs_f32() / span;
+            pixels[y as usize * width + x as usize] = 255 - (lateness * 254.0) as u8;
+        }
+    }
+    encode_png(width, height, GRAYSCALE, &pixels)
+}
+
+/// How a line of an SVG plot is drawn
+#[derive(Clone, Copy, PartialEq)]
+pub enum SvgStroke {
+    Solid,
+    Dashed,
+    /// A separate dot at each point
+    Dots,
+}
+
+/// A line of an SVG plot, with points as fractions of the plot area from its bottom left
+pub struct SvgLine {
+    pub points: Vec<(f32, f32)>,
+    pub color: Color32,
+    pub width: f32,
+    pub stroke: SvgStroke,
+}
+
+/// A line plot as a standalone SVG document on a white background, with ticks as
+/// `(fraction along the axis, label)` and the axes and text in `color`
+///
+/// A solid line with a single point is drawn as a dot, as on screen.
+pub fn plot_svg(
+    x_label: &str,
+    y_label: &str,
+    x_ticks: &[(f32, String)],
+    y_ticks: &[(f32, String)],
+    lines: &[SvgLine],
+    color: Color32,
+) -> String {
+    const WIDTH: f32 = 800.0;
+    const HEIGHT: f32 = 500.0;
+    // Plot area, leaving room for the tick labels and axis labels
+    let (left, right, top, bottom) = (70.0, WIDTH - 20.0, 20.0, HEIGHT - 60.0);
+    let to_svg = |(x, y): (f32, f32)| (left + x * (right - left), bottom - y * (bottom - top));
+    let (stroke, fill) = (svg_paint("stroke", color), svg_paint("fill", color));
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{WIDTH}" height="{HEIGHT}" viewBox="0 0 {WIDTH} {HEIGHT}" font-family="sans-serif" font-size="14">"#
+    );
+    let _ = writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#);
+    let _ = writeln!(
+        svg,
+        r#"<clipPath id="plot"><rect x="{left}" y="{top}" width="{}" height="{}"/></clipPath>"#,
+        right - left,
+        bottom - top
+    );
+
+    let _ = writeln!(
+        svg,
+        r#"<path d="M{left},{top} V{bottom} H{right}" fill="none" {stroke}/>"#
+    );
+    for (fraction, label) in x_ticks {
+        let (x, _) = to_svg((*fraction, 0.0));
+        let _ = writeln!(
+            svg,
+            r#"<line x1="{x:.2}" y1="{bottom}" x2="{x:.2}" y2="{}" {stroke}/><text x="{x:.2}" y="{}" text-anchor="middle" font-size="11" {fill}>{}</text>"#,
+            bottom + 4.0,
+            bottom + 18.0,
+            escape_xml(label)
+        );
+    }
+    for (fraction, label) in y_ticks {
+        let (_, y) = to_svg((0.0, *fraction));
+        let _ = writeln!(
+            svg,
+            r#"<line x1="{}" y1="{y:.2}" x2="{left}" y2="{y:.2}" {stroke}/><text x="{}" y="{y:.2}" text-anchor="end" dominant-baseline="middle" font-size="11" {fill}>{}</text>"#,
+            left - 4.0,
+            left - 6.0,
+            escape_xml(label)
+        );
+    }
+    let _ = writeln!(
+        svg,
+        r#"<text x="{}" y="{}" text-anchor="middle" {fill}>{}</text>"#,
+        (left + right) / 2.0,
+        HEIGHT - 15.0,
+        escape_xml(x_label)
+    );
+    let _ = writeln!(
+        svg,
+        r#"<text transform="translate(20,{}) rotate(-90)" text-anchor="middle" dominant-baseline="middle" {fill}>{}</text>"#,
+        (top + bottom) / 2.0,
+        escape_xml(y_label)
+    );
+
+    let _ = writeln!(svg, r#"<g clip-path="url(#plot)" fill="none">"#);
+    for line in lines {
+        let width = line.width;
+        let points: Vec<(f32, f32)> = line.points.iter().copied().map(to_svg).collect();
+        match (line.stroke, &points[..]) {
+            (_, []) => {}
+            (SvgStroke::Dots, _) | (SvgStroke::Solid, [_]) => {
+                for (x, y) in points {
+                    let _ = writeln!(
+                        svg,
+                        r#"<circle cx="{x:.2}" cy="{y:.2}" r="{width}" {}/>"#,
+                        svg_paint("fill", line.color)
+                    );
+                }
+            }
+            (SvgStroke::Solid | SvgStroke::Dashed, _) => {
+                let dashes = if line.stroke == SvgStroke::Dashed {
+                    r#" stroke-dasharray="6 4""#
+                } else {
+                    ""
+                };
+                let _ = write!(svg, r#"<polyline points=""#);
+                for (x, y) in points {
+                    let _ = write!(svg, "{x:.2},{y:.2} ");
+                }
+                let _ = writeln!(
+                    svg,
+                    r#"" {} stroke-width="{width}" stroke-linejoin="round"{dashes}/>"#,
+                    svg_paint("stroke", line.color)
+                );
+            }
+        }
+    }
+    svg.push_str("</g>\n</svg>\n");
+    svg
+}
+
+/// SVG attributes painting `property` (`fill` or `stroke`) in `color`, with any transparency
+/// as its opacity
+fn svg_paint(property: &str, color: Color32) -> String {
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    let paint = format!(r##"{property}="#{r:02x}{g:02x}{b:02x}""##);
+    if a == u8::MAX {
+        paint
+    } else {
+        format!(r#"{paint} {property}-opacity="{:.3}""#, a as f32 / 255.0)
+    }
+}
+
+/// `text` with the characters XML reserves escaped
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// PNG color types for 8-bit grayscale and RGB pixels, with their bytes per pixel
+pub const GRAYSCALE: (u8, usize) = (0, 1);
+const RGB: (u8, usize) = (2, 3);
+
+/// Encodes 8-bit `pixels` of `color_type`, row by row, as an uncompressed PNG
+pub fn encode_png(
+    width: usize,
+    height: usize,
+    (color_type, channels): (u8, usize),
+    pixels: &[u8],
+) -> Vec<u8> {
+    // Each scanline is prefixed with filter type 0 (none)
+    let mut raw = Vec::with_capacity((width * channels + 1) * height);
+    for row in pixels.chunks((width * channels).max(1)) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+
+    // A zlib stream of stored deflate blocks, which hold at most 65535 bytes each
+    let mut zlib = vec![0x78, 0x01];
+    let mut blocks = raw.chunks(u16::MAX as usize).peekable();
+    if blocks.peek().is_none() {
+        zlib.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let len = block.len() as u16;
+        zlib.push(blocks.peek().is_none() as u8);
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // 8 bits per channel, default compression and filtering, no interlacing
+    header.extend_from_slice(&[8, color_type, 0, 0, 0]);
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    for (kind, data) in [(b"IHDR", &header), (b"IDAT", &zlib), (b"IEND", &Vec::new())] {
+        png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        let start = png.len();
+        png.extend_from_slice(kind);
+        png.extend_from_slice(data);
+        let crc = crc32(&png[start..]);
+        png.extend_from_slice(&crc.to_be_bytes());
+    }
+    png
+}
+
+pub fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// The snapshot nearest to each multiple of `interval`, up to the latest snapshot
+pub fn sample_every(
+    stats: &[PandemicSnapshot],
+    interval: Duration,
+) -> Vec<(Duration, &PandemicSnapshot)> {
+    let mut samples = Vec::new();
+    let (Some(last), false) = (stats.last(), interval.is_zero()) else {
+        return samples;
+    };
+
+    let mut time = Duration::ZERO;
+    while time <= last.time {
+        samples.push((time, nearest_snapshot(stats, time)));
+        time += interval;
+    }
+    samples
+}
+
+/// Snapshot closest in time to `time`; `stats` must be non-empty and sorted by time
+fn nearest_snapshot(stats: &[PandemicSnapshot], time: Duration) -> &PandemicSnapshot {
+    let after = stats.partition_point(|stat| stat.time < time);
+    match (after.checked_sub(1), stats.get(after)) {
+        (Some(before), Some(next)) if time - stats[before].time < next.time - time => {
+            &stats[before]
+        }
+        (_, Some(next)) => next,
+        (Some(before), None) => &stats[before],
+        (None, None) => unreachable!("stats must be non-empty"),
+    }
+}
+
+/// Saves `contents` as `file_name`, returning a status message for the UI
+///
+/// Natively the file is written to the working directory; on the web it is offered as a
+/// browser download.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_file(file_name: &str, contents: &[u8]) -> String {
+    match std::fs::write(file_name, contents) {
+        Ok(()) => format!("Saved {file_name}"),
+        Err(e) => format!("Failed to save {file_name}: {e}"),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn save_file(file_name: &str, contents: &[u8]) -> String {
+    use eframe::wasm_bindgen::{JsCast as _, JsValue};
+
+    let download = || -> Result<(), JsValue> {
+        let document = web_sys::window()
+            .and_then(|window| window.document())
+            .ok_or("No document")?;
+        let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(contents));
+        let blob = web_sys::Blob::new_with_u8_array_sequence(&parts)?;
+        let url = web_sys::Url::create_object_url_with_blob(&blob)?;
+
+        let anchor: web_sys::HtmlAnchorElement = document.create_element("a")?.dyn_into()?;
+        anchor.set_href(&url);
+        anchor.set_download(file_name);
+        anchor.click();
+        web_sys::Url::revoke_object_url(&url)
+    };
+
+    match download() {
+        Ok(()) => format!("Downloaded {file_name}"),
+        Err(e) => format!("Failed to download {file_name}: {e:?}"),
+    }
+}
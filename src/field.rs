@@ -0,0 +1,153 @@
+//! Aggregate model for populations too large to simulate person by person: each contact cell
+//! holds counts of people in each state, which react within the cell and diffuse between
+//! neighbouring cells
+
+use crate::{
+    Palette, Params, X_MAX_FLOAT, Y_MAX_FLOAT, letterbox,
+    ode::{Compartments, Rates},
+};
+use egui::{Color32, Pos2, Rect, Shape, Ui, Vec2};
+
+/// Integration step, in days
+const DT: f64 = 0.05;
+/// Largest share of a cell's people that may leave for each neighbour in one step, beyond which
+/// the explicit diffusion step turns unstable
+const MAX_EXCHANGE: f64 = 0.25;
+
+/// Compartment counts for each contact cell, row by row
+#[derive(Clone)]
+pub struct Field {
+    /// Agents whose contact density the field reproduces
+    agents: usize,
+    width: usize,
+    height: usize,
+    cell_size: f32,
+    cells: Vec<Compartments>,
+}
+
+impl Field {
+    /// `params.field_population` spread evenly over the grid, standing in for `agents` people of
+    /// whom `infected` start infected; the same fraction of the field does, in the centre cell
+    pub fn new(infected: usize, agents: usize, params: &Params) -> Self {
+        let cell_size = params.cell_size;
+        let population = params.field_population as f64;
+        let width = (X_MAX_FLOAT / cell_size).ceil().max(1.0) as usize;
+        let height = (Y_MAX_FLOAT / cell_size).ceil().max(1.0) as usize;
+        let per_cell = population / (width * height) as f64;
+        let cells = vec![
+            Compartments {
+                healthy: per_cell,
+                infected: 0.0,
+                recovered: 0.0,
+                dead: 0.0,
+            };
+            width * height
+        ];
+        let mut field = Self {
+            agents,
+            width,
+            height,
+            cell_size,
+            cells,
+        };
+        field.seed(infected);
+        field
+    }
+
+    /// Infects the same fraction of the field as `infected` is of the agents, in the centre
+    /// cell, as far as it has healthy people
+    pub fn seed(&mut self, infected: usize) {
+        let totals = self.totals();
+        let population = totals.healthy + totals.infected + totals.recovered + totals.dead;
+        let centre = &mut self.cells[self.height / 2 * self.width + self.width / 2];
+        let seeded = (population * infected as f64 / self.agents.max(1) as f64).min(centre.healthy);
+        centre.infected += seeded;
+        centre.healthy -= seeded;
+    }
+
+    /// Advances by `days`, with everyone alive moving `params.field_diffusion` of the way to each
+    /// neighbouring cell per day; nobody crosses the edges
+    pub fn advance(&mut self, days: f64, params: &Params) {
+        let rates = &Rates::new(params, self.agents);
+        let steps = (days / DT).ceil().max(1.0);
+        let dt = days / steps;
+        let exchange = (params.field_diffusion as f64 * dt).min(MAX_EXCHANGE);
+        for _ in 0..steps as usize {
+            for cell in &mut self.cells {
+                *cell = cell.add_scaled(cell.derivative(rates), dt);
+            }
+
+            let before = self.cells.clone();
+            for y in 0..self.height {
+                for x in 0..self.width {
+                    let here = y * self.width + x;
+                    let neighbours = [
+                        (x > 0).then(|| here - 1),
+                        (x + 1 < self.width).then(|| here + 1),
+                        (y > 0).then(|| here - self.width),
+                        (y + 1 < self.height).then(|| here + self.width),
+                    ];
+                    for there in neighbours.into_iter().flatten() {
+                        // The dead stay where they fell
+                        let mut flow = before[there].add_scaled(before[here], -1.0);
+                        flow.dead = 0.0;
+                        self.cells[here] = self.cells[here].add_scaled(flow, exchange);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Counts across the whole grid
+    pub fn totals(&self) -> Compartments {
+        let zero = Compartments {
+            healthy: 0.0,
+            infected: 0.0,
+            recovered: 0.0,
+            dead: 0.0,
+        };
+        self.cells
+            .iter()
+            .fold(zero, |total, &cell| total.add_scaled(cell, 1.0))
+    }
+
+    /// Draws each cell in the mix of its people's colours, fading out where few are left alive
+    pub fn render(&self, ui: &mut Ui, palette: &Palette) -> Rect {
+        let avail = ui.available_size() - Vec2 { x: 10.0, y: 10.0 };
+        let (size, offset) = letterbox(avail);
+        let grid_rect = Rect::from_min_size(Pos2::new(offset.x + 5.0, offset.y + 5.0), size);
+        let scale = size.x / X_MAX_FLOAT;
+
+        let living = |cell: &Compartments| cell.healthy + cell.infected + cell.recovered;
+        let densest = self.cells.iter().map(living).fold(0.0, f64::max);
+        if densest <= 0.0 {
+            return grid_rect;
+        }
+        ui.painter()
+            .extend(self.cells.iter().enumerate().filter_map(|(i, cell)| {
+                let alive = living(cell);
+                if alive <= 0.0 {
+                    return None;
+                }
+                let mix = |channel: fn(Color32) -> u8| {
+                    let shade = (cell.healthy * channel(palette.healthy) as f64
+                        + cell.infected * channel(palette.infected) as f64
+                        + cell.recovered * channel(palette.recovered) as f64)
+                        / alive;
+                    shade.round() as u8
+                };
+                let color = Color32::from_rgba_unmultiplied(
+                    mix(|c| c.r()),
+                    mix(|c| c.g()),
+                    mix(|c| c.b()),
+                    (255.0 * alive / densest).round() as u8,
+                );
+                let (x, y) = ((i % self.width) as f32, (i / self.width) as f32);
+                let min = grid_rect.min + Vec2::new(x, y) * self.cell_size * scale;
+                let cell_rect = Rect::from_min_size(min, Vec2::splat(self.cell_size * scale))
+                    .intersect(grid_rect);
+                Some(Shape::rect_filled(cell_rect, 0.0, color))
+            }));
+        grid_rect
+    }
+}
@@ -1,19 +1,59 @@
 use eframe::App;
 use egui::{
-    Button, Color32, ComboBox, FontId, Frame, Grid, Label, Margin, Pos2, Shape, Slider, Stroke, Ui,
-    Vec2,
-    ahash::{HashMap, HashMapExt},
-    epaint::{CircleShape, TextShape},
+    Align2, Button, Color32, ComboBox, DragValue, FontId, Frame, Grid, Label, Margin, Painter,
+    Pos2, Rect, Response, RichText, ScrollArea, Shape, Slider, Stroke, Theme, Ui, Vec2,
+    ahash::{HashMap, HashMapExt, HashSet, HashSetExt},
+    emath::Numeric,
+    epaint::{
+        CircleShape, ColorImage, Mesh, RectShape, StrokeKind, TextShape, TextureHandle, TextureId,
+        textures::{TextureFilter, TextureOptions},
+    },
 };
-use rand::{random_bool, random_range};
+use serde::{Deserialize, Serialize};
 use std::{
+    collections::{BTreeMap, VecDeque},
     f32::{self, consts::PI},
     fmt::Display,
+    ops::RangeInclusive,
 };
 use web_time::{Duration, Instant};
 
+#[cfg(not(target_arch = "wasm32"))]
+mod cli;
+mod ensemble;
+mod export;
+mod field;
+mod ode;
+mod rewind;
+mod rng;
+mod visibility;
+use ensemble::{
+    EnsembleJob, EnsembleResults, Histogram, Measure, RunOutcome, Sensitivity, SensitivityJob,
+};
+use export::{Config, Filmstrip, LineList, Outcome, SvgLine, SvgStroke, Trajectories};
+use field::Field;
+use rewind::Checkpoint;
+use rng::{random_bool, random_range};
+
 #[cfg(not(target_arch = "wasm32"))]
 fn main() {
+    let args = std::env::args().skip(1);
+    if std::env::args().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{}", cli::USAGE);
+        return;
+    }
+    let startup = cli::Startup::from_args(args).unwrap_or_else(|e| {
+        eprintln!("{e}\n\n{}", cli::USAGE);
+        std::process::exit(2);
+    });
+    if startup.headless {
+        let seed = startup.seed.unwrap_or_else(rand::random);
+        rng::reseed(Some(seed));
+        let outcome = RunOutcome::simulate(startup.infected, startup.total, &startup.params);
+        println!("{}", cli::summary_line(seed, startup.total, &outcome));
+        return;
+    }
+
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size(Vec2 { x: 800., y: 700. })
@@ -23,7 +63,17 @@ fn main() {
     eframe::run_native(
         "pandemic",
         native_options,
-        Box::new(|_| Ok(Box::new(Pandemic::new(5, 500)))),
+        Box::new(|_| {
+            rng::reseed(startup.seed);
+            let mut app = Pandemic::with_params(startup.infected, startup.total, startup.params);
+            // A seed on the command line starts the app in deterministic mode with it
+            if let Some(seed) = startup.seed {
+                app.deterministic = true;
+                app.seed = seed;
+                app.active_seed = Some(seed);
+            }
+            Ok(Box::new(app))
+        }),
     )
     .unwrap();
 }
@@ -50,7 +100,13 @@ fn main() {
             .start(
                 canvas,
                 web_options,
-                Box::new(|_| Ok(Box::new(Pandemic::new(5, 500)))),
+                Box::new({
+                    let document = document.clone();
+                    |cc| {
+                        visibility::watch(document, cc.egui_ctx.clone());
+                        Ok(Box::new(Pandemic::new(5, 500)))
+                    }
+                }),
             )
             .await;
 
@@ -77,16 +133,110 @@ struct Pandemic {
     total: usize,
 
     // Public params
-    infection_prob: f32,
-    infection_time_s: f32,
-    death_prob: f32,
+    params: Params,
+    /// Simulated time per wall-clock time; in deterministic mode, model steps per frame
     step_speed: f32,
     paused: bool,
+    /// Advance by `step_speed` fixed steps per frame from a seeded start, so every run with
+    /// the same seed and settings plays out identically regardless of frame rate
+    deterministic: bool,
+    seed: u64,
+    /// Seed the current run started from, if it was seeded
+    active_seed: Option<u64>,
+    /// Fractional fixed steps carried over to the next frame, for step speeds below one
+    steps_owed: f32,
+    /// Take fewer than `step_speed` fixed steps while taking them takes longer than
+    /// `step_budget_ms` per frame; the cost of a wall-clock step doesn't depend on its speed.
+    /// Off by default, since it makes how far a frame goes depend on the machine
+    speed_governor: bool,
+    step_budget_ms: f32,
+    /// Fraction of `step_speed` currently allowed by the governor
+    throttle: f32,
+    /// Outcome of the latest reproducibility check
+    replay_status: Option<String>,
     graph: GraphOptions,
+    display: DisplayOptions,
+    confirm_reset: bool,
+    reset_pending: bool,
+    /// Pause when the immune fraction first reaches the herd immunity threshold
+    pause_at_herd_immunity: bool,
+    /// Pause at the first death and mark where it happened
+    pause_at_first_death: bool,
+    /// Parameter the up and down arrow keys adjust, if any
+    nudge: Option<Nudge>,
+    /// Values the schedule has changed this run, as they were before, so they can be put back
+    schedule_originals: Vec<(Nudge, f32)>,
+
+    // Ensemble
+    ensemble_runs: usize,
+    /// Worker threads an ensemble is spread across, natively
+    ensemble_threads: usize,
+    /// The ensemble still running, if any
+    ensemble_job: Option<EnsembleJob>,
+    ensemble: Option<EnsembleResults>,
+    sensitivity_measure: Measure,
+    /// Fraction parameters are scaled down and up by in sensitivity analysis
+    sensitivity_perturbation: f32,
+    /// The sensitivity analysis still running, if any
+    sensitivity_job: Option<SensitivityJob>,
+    sensitivity: Option<Sensitivity>,
+
+    // Rewind
+    /// Simulated days between checkpoints of the full state
+    checkpoint_interval_days: f32,
+    /// Oldest checkpoints are dropped beyond this many
+    max_checkpoints: usize,
+    /// Oldest first
+    checkpoints: VecDeque<Checkpoint>,
+    rewind_to_day: f32,
+
+    // Surveillance
+    /// Fraction of true infections that show up in the reported counts
+    reporting_fraction: f32,
+    /// Standard deviation of the reported counts, relative to their expected value
+    reporting_noise: f32,
+    /// Infected as reported once a day, an undercounted and noisy view of the truth
+    reported: Vec<(Duration, f32)>,
+
+    // Export
+    title: String,
+    notes: String,
+    export_interval_days: f32,
+    export_status: Option<String>,
+    /// Clicking on the grid tags or untags the nearest person for tracking
+    tag_mode: bool,
+    /// Ids of the people whose trajectories are recorded
+    tagged: HashSet<usize>,
+    trajectories: Trajectories,
+    /// Days between filmstrip frames, or 0 to take none
+    filmstrip_interval_days: f32,
+    /// Pause the run each time a filmstrip frame is taken
+    filmstrip_pause: bool,
+    filmstrip: Filmstrip,
+    /// Configs dropped onto the window for comparison, by file name; at most two, oldest first
+    compared_configs: VecDeque<(String, Config)>,
 
     // Data
     grid: SpatialGrid,
+    /// People in each cell, in place of `grid`, under `SimulationMode::Field`
+    field: Option<Field>,
+    /// Disc that batched dots are drawn with, uploaded on first render
+    dot_texture: Option<TextureHandle>,
+    profile: StepProfile,
+    heat: HeatMap,
+    /// Contamination left in each cell by infectious people of each strain, under environmental
+    /// transmission
+    contamination: HashMap<(i32, i32), [f32; 2]>,
+    infection_counts: HashMap<(i32, i32), u32>,
+    /// When each person last shared a cell with each other person, by id; only kept while
+    /// contact tracing is on
+    contacts: HashMap<usize, HashMap<usize, Duration>>,
     last_frame_time: Instant,
+    /// Whole simulated days elapsed as of the last frame, and when the latest began
+    last_day: u64,
+    day_started: Instant,
+    /// Page visibility changes already accounted for in `last_frame_time`
+    visibility_changes: usize,
     time_elapsed: Duration,
 
     // Stats
@@ -94,17 +244,110 @@ struct Pandemic {
     num_infected: usize,
     num_recovered: usize,
     num_dead: usize,
+    /// Healthy people currently protected by a vaccine
+    num_vaccinated: usize,
+    num_innate_immune: usize,
+    /// Everyone ever infected, including the initial seed
+    num_cumulative_infected: usize,
+    /// Infections of people who had already been infected before
+    num_reinfections: usize,
+    line_list: LineList,
+    /// The initial infected are yet to be introduced, on `params.seed_day`
+    awaiting_seed: bool,
+    /// Where the initial infected were when they were introduced
+    origins: Vec<Pos2>,
+    /// An earlier run's stats, drawn faintly behind the live ones
+    pinned: Option<Vec<PandemicSnapshot>>,
+    /// Real case counts by day, overlaid on the Infected graph, and where they came from
+    reference: Option<(String, Vec<(f32, f32)>)>,
+    /// Stretch the reference in time so its peak lines up with the simulation's
+    reference_align_peaks: bool,
+    /// CSV being pasted into the reference loader, while it's open
+    reference_paste: Option<String>,
+    /// When the immune fraction first reached the herd immunity threshold
+    herd_immunity_at: Option<Duration>,
+    /// Where and when the first death happened
+    first_death: Option<(Pos2, Duration)>,
+    /// Milestones passed so far, with the wall-clock time each was noticed for its ping
+    milestones: Vec<(Milestone, Instant)>,
+    /// Most people infected at once so far
+    peak_infected: usize,
+    front: InfectionFront,
+    /// Longest chain of transmissions from a seed so far
+    max_generation: u32,
+    /// Only every this many frames is recorded into `stats`
+    record_every_n_frames: usize,
+    frames_since_record: usize,
     stats: Vec<PandemicSnapshot>,
 }
 
 impl App for Pandemic {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if ctx.theme() != self.display.theme {
+            ctx.set_theme(self.display.theme);
+        }
+        if self.awaiting_seed
+            && !ctx.wants_keyboard_input()
+            && ctx.input(|input| input.key_pressed(egui::Key::I))
+        {
+            self.introduce_infection();
+        }
+        if let Some(nudge) = self.nudge
+            && !ctx.wants_keyboard_input()
+        {
+            let (up, down, shift) = ctx.input(|input| {
+                (
+                    input.key_pressed(egui::Key::ArrowUp),
+                    input.key_pressed(egui::Key::ArrowDown),
+                    input.modifiers.shift,
+                )
+            });
+            let (value, step, range) = nudge.target(&mut self.params);
+            let step = if shift { step * 10.0 } else { step };
+            if up {
+                *value = (*value + step).clamp(*range.start(), *range.end());
+            }
+            if down {
+                *value = (*value - step).clamp(*range.start(), *range.end());
+            }
+        }
+        for file in ctx.input(|input| input.raw.dropped_files.clone()) {
+            let (name, contents) = dropped_file(&file);
+            if name.to_lowercase().ends_with(".csv") {
+                self.load_reference(name, contents);
+            } else {
+                self.compare_config(name, contents);
+            }
+        }
+        self.reference_loader(ctx);
+
+        // Panels can be dragged to size; eframe remembers their sizes between sessions
         egui::TopBottomPanel::bottom("info_panel")
-            .exact_height(450.)
+            .resizable(true)
+            .default_height(450.)
+            .min_height(MIN_PANEL_SIZE)
             .show(ctx, |ui| {
                 egui::SidePanel::left("params")
-                    .exact_width(250.)
-                    .show_inside(ui, |ui| self.params_ui(ui));
+                    .resizable(true)
+                    .default_width(250.)
+                    .min_width(MIN_PANEL_SIZE)
+                    .show_inside(ui, |ui| {
+                        ScrollArea::vertical().show(ui, |ui| self.params_ui(ui));
+                    });
+                if !self.compared_configs.is_empty() {
+                    egui::SidePanel::right("config_diff")
+                        .resizable(true)
+                        .default_width(350.)
+                        .min_width(MIN_PANEL_SIZE)
+                        .show_inside(ui, |ui| self.config_diff_ui(ui));
+                }
+                if self.display.show_daily_table {
+                    egui::SidePanel::right("daily_table")
+                        .resizable(true)
+                        .default_width(300.)
+                        .min_width(MIN_PANEL_SIZE)
+                        .show_inside(ui, |ui| self.daily_table_ui(ui));
+                }
 
                 Frame::new()
                     .outer_margin(Margin::symmetric(20, 30))
@@ -113,15 +356,76 @@ impl App for Pandemic {
                     });
             });
 
+        // Skip over time spent in a hidden tab instead of jumping ahead on return
+        let hidden = self.display.pause_when_hidden && visibility::hidden();
+        if self.display.pause_when_hidden && visibility::changes() != self.visibility_changes {
+            self.visibility_changes = visibility::changes();
+            self.last_frame_time = Instant::now();
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
-            self.grid.render(ui);
-            if !self.paused {
+            let palette = Palette::of(&self.display);
+            if self.population() == 0 {
+                ui.centered_and_justified(|ui| {
+                    ui.add(Label::new(
+                        "No population: raise \"Total people\" and reset to start",
+                    ));
+                });
+                return;
+            }
+            let dot_texture = self
+                .dot_texture
+                .get_or_insert_with(|| load_dot_texture(ctx))
+                .id();
+            let grid_rect = if let Some(field) = &self.field {
+                field.render(ui, palette)
+            } else {
+                let grid_rect = self.grid.render(
+                    ui,
+                    &self.display,
+                    &self.cell_overlay(),
+                    &self.params,
+                    dot_texture,
+                    self.display.show_origins.then(|| {
+                        let phase = (self.time_elapsed.as_secs_f32() / ORIGIN_PULSE_S).fract();
+                        (&self.origins[..], phase)
+                    }),
+                );
+                if self.paused && self.display.show_contacts {
+                    self.grid.render_contacts(ui, grid_rect, palette);
+                }
+                self.tagging(ui, grid_rect, palette);
+                grid_rect
+            };
+            draw_legend(ui, palette, &self.display, self.params.strain_b_seeds > 0);
+            if let (true, Some((pos, time))) = (self.pause_at_first_death, self.first_death)
+                && self.time_elapsed < time + FIRST_DEATH_NOTE
+            {
+                draw_first_death(ui, grid_rect, pos, time, palette);
+            }
+            if self.display.show_profiler {
+                draw_profiler(ui, &self.profile, palette);
+            }
+            if !self.paused && !hidden {
                 self.step();
             }
+
+            // Pulse the day counter as each simulated day passes
+            let day = self.time_elapsed.as_secs();
+            if day != self.last_day {
+                self.last_day = day;
+                self.day_started = Instant::now();
+            }
+            if self.display.show_day_counter {
+                let pulse = 1.0 - self.day_started.elapsed().as_secs_f32() / DAY_PULSE_S;
+                draw_day_counter(ui, day, pulse.max(0.0), palette);
+            }
         });
 
-        // Re-render (hot loop)
-        ctx.request_repaint();
+        // Re-render at the target frame rate while running; when paused, input triggers repaints
+        if !self.paused && !hidden {
+            ctx.request_repaint_after(Duration::from_secs_f32(1.0 / self.display.target_fps));
+        }
     }
 }
 
@@ -130,28 +434,351 @@ const Y_MAX: i32 = 50;
 const X_MAX_FLOAT: f32 = X_MAX as f32;
 const Y_MAX_FLOAT: f32 = Y_MAX as f32;
 
+/// Amount of motion per ms
+const MOVE_AMOUNT: f32 = 0.01;
+
+/// Factors the speed governor scales its throttle by each frame over and under budget
+const THROTTLE_BACKOFF: f32 = 0.9;
+const THROTTLE_RECOVERY: f32 = 1.02;
+/// The governor never slows the simulation below this fraction of its set speed
+const MIN_THROTTLE: f32 = 0.05;
+
+/// Upper bounds of the population sliders
+const MAX_INIT_INFECTED: usize = 1000;
+const MAX_TOTAL: usize = 10000;
+/// Populations above this get a warning that they may not keep up with the frame rate
+const SLOW_TOTAL: usize = 5000;
+
+const HISTOGRAM_BINS: usize = 20;
+/// Simulated milliseconds a dead person lingers on the grid while fading out
+const DEATH_FADE_MS: f32 = 500.0;
+/// Runs longer than this ask for confirmation before being reset
+const RESET_CONFIRM_AFTER: Duration = Duration::from_secs(5);
+/// Trailing window of simulated time over which transition rates are averaged
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+/// Simulated seconds for an infection trail to fade to half intensity
+const HEAT_HALF_LIFE_S: f32 = 1.0;
+/// Smallest width and height, in points, worth drawing a graph into
+const MIN_PLOT_SIZE: f32 = 20.0;
+/// Smallest a panel can be dragged to, in points
+const MIN_PANEL_SIZE: f32 = 100.0;
+/// Most day ticks labelled along a graph's time axis
+const MAX_DAY_TICKS: usize = 6;
+/// How long after the first death its location stays marked, in simulated time
+const FIRST_DEATH_NOTE: Duration = Duration::from_secs(3);
+/// Wall-clock seconds the day counter stays highlighted after each new day
+const DAY_PULSE_S: f32 = 0.4;
+/// Radius, in grid units, of the cluster each strain's seeds start in
+const STRAIN_CLUSTER_RADIUS: f32 = 3.0;
+/// Most contact edges drawn within one cell, so crowded cells don't turn into a solid mass
+const MAX_CONTACT_EDGES: usize = 15;
+/// Width in px of the hospital drawn beside the grid, and the gap between them
+const HOSPITAL_WIDTH: f32 = 90.0;
+const HOSPITAL_GAP: f32 = 10.0;
+/// Farthest, in grid units, a click can be from someone and still tag them
+const TAG_RADIUS: f32 = 2.0;
+/// Wall-clock seconds a counter stays highlighted after a milestone it shows
+const MILESTONE_PING_S: f32 = 1.5;
+/// Fraction of the peak so far that infections must fall to for the peak to count as passed
+const PEAK_PASSED_RATIO: f32 = 0.9;
+/// Simulated days between pulses of the outbreak origin markers
+const ORIGIN_PULSE_S: f32 = 2.0;
+/// Trail intensity added to a cell per new infection
+const HEAT_BUMP: f32 = 0.5;
+/// How far back contact tracing looks for a detected case's cell-mates
+const CONTACT_MEMORY: Duration = Duration::from_secs(5);
+/// Fraction of the population that must be infected at once for a wave to count
+const WAVE_MIN_PEAK: f32 = 0.01;
+/// Fraction of a wave's peak infections must fall below to end it
+const WAVE_TROUGH_RATIO: f32 = 0.5;
+/// How far past the present the projection line runs
+const PROJECTION: Duration = Duration::from_secs(14);
+/// Recent history the projection's transmission rate is fitted to
+const PROJECTION_FIT_WINDOW: Duration = Duration::from_secs(3);
+/// Simulated time per model step in deterministic mode, one 60 fps frame's worth
+const DETERMINISTIC_DT: Duration = Duration::from_micros(16_667);
+/// Ranges of the parameters the arrow keys and schedule can change, shared with their sliders
+const PROBABILITY_RANGE: RangeInclusive<f32> = 0.0..=1.0;
+const INFECTION_TIME_RANGE: RangeInclusive<f32> = 0.0..=30.0;
+const CELL_SIZE_RANGE: RangeInclusive<f32> = 0.25..=10.0;
+const SPEED_RANGE: RangeInclusive<f32> = 0.0..=2.0;
+
 impl Pandemic {
+    /// Natively the starting scenario comes from `cli::Startup` instead
+    #[cfg(any(test, target_arch = "wasm32"))]
     fn new(infected: usize, total: usize) -> Self {
-        Self {
-            init_infected: infected,
+        Self::with_params(infected, total, Params::default())
+    }
+
+    fn with_params(infected: usize, total: usize, params: Params) -> Self {
+        // The sliders are independent, so the seed may exceed the population
+        let infected = infected.min(total);
+        // A delayed seed leaves everyone healthy until `seed_day`
+        let awaiting_seed = params.seed_day > 0.0 && infected > 0;
+        let init_infected = infected;
+        let infected = if awaiting_seed { 0 } else { infected };
+        // The field takes the agents' place, simulating them only for their contact density
+        let field =
+            (params.mode == SimulationMode::Field).then(|| Field::new(infected, total, &params));
+        let grid = if field.is_some() {
+            SpatialGrid::new_with_capacity(0, 0, &params)
+        } else {
+            SpatialGrid::new_with_capacity(infected, total, &params)
+        };
+        let num_innate_immune = grid
+            .0
+            .values()
+            .flatten()
+            .filter(|person| person.innate_immune)
+            .count();
+        let origins = infected_positions(&grid);
+        let front =
+            InfectionFront::seeded(&grid, params.cell_size, params.boundary, Duration::ZERO);
+        let mut pandemic = Self {
+            init_infected,
             total,
 
-            infection_prob: 0.5,
-            infection_time_s: 14.0,
-            death_prob: 0.1,
+            params,
             step_speed: 1.0,
             paused: false,
+            deterministic: false,
+            active_seed: None,
+            seed: 0,
+            steps_owed: 0.0,
+            speed_governor: false,
+            step_budget_ms: 12.0,
+            throttle: 1.0,
+            replay_status: None,
             graph: GraphOptions::Infected,
+            display: DisplayOptions::default(),
+            confirm_reset: true,
+            reset_pending: false,
+            pause_at_herd_immunity: false,
+            pause_at_first_death: false,
+            nudge: None,
+            schedule_originals: Vec::new(),
+
+            ensemble_runs: 50,
+            ensemble_threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            ensemble_job: None,
+            ensemble: None,
+            sensitivity_measure: Measure::Deaths,
+            sensitivity_perturbation: 0.2,
+            sensitivity_job: None,
+            sensitivity: None,
+
+            checkpoint_interval_days: 5.0,
+            max_checkpoints: 20,
+            checkpoints: VecDeque::new(),
+            rewind_to_day: 0.0,
+
+            reporting_fraction: 0.5,
+            reporting_noise: 0.2,
+            reported: Vec::new(),
 
-            grid: SpatialGrid::new_with_capacity(infected, total),
+            title: String::new(),
+            notes: String::new(),
+            export_interval_days: 1.0,
+            export_status: None,
+            tag_mode: false,
+            tagged: HashSet::new(),
+            trajectories: Trajectories::default(),
+            filmstrip_interval_days: 0.0,
+            filmstrip_pause: false,
+            filmstrip: Filmstrip::default(),
+            compared_configs: VecDeque::new(),
+
+            grid,
+            field,
+            dot_texture: None,
+            profile: StepProfile::default(),
+            heat: HeatMap::new(),
+            contamination: HashMap::new(),
+            infection_counts: HashMap::new(),
+            contacts: HashMap::new(),
             last_frame_time: Instant::now(),
+            last_day: 0,
+            day_started: Instant::now(),
+            visibility_changes: visibility::changes(),
             time_elapsed: Duration::ZERO,
 
             num_healthy: total - infected,
             num_infected: infected,
             num_recovered: 0,
             num_dead: 0,
+            num_vaccinated: 0,
+            num_innate_immune,
+            num_cumulative_infected: infected,
+            num_reinfections: 0,
+            line_list: LineList::seeded(0..infected),
+            awaiting_seed,
+            origins,
+            pinned: None,
+            reference: None,
+            reference_align_peaks: false,
+            reference_paste: None,
+            herd_immunity_at: None,
+            first_death: None,
+            milestones: Vec::new(),
+            peak_infected: infected,
+            front,
+            max_generation: 0,
+            record_every_n_frames: 1,
+            frames_since_record: 0,
             stats: Vec::new(),
+        };
+        if pandemic.field.is_some() {
+            pandemic.count_field();
+            pandemic.num_cumulative_infected = pandemic.num_infected;
+            pandemic.peak_infected = pandemic.num_infected;
+            pandemic.line_list = LineList::seeded([]);
+        }
+        pandemic
+    }
+
+    /// Restarts from fresh initial conditions, keeping parameters and settings
+    fn reset(&mut self) {
+        self.revert_schedule();
+        if self.deterministic {
+            rng::reseed(Some(self.seed));
+        }
+        let fresh = Self::with_params(self.init_infected, self.total, self.params.clone());
+        let old = std::mem::replace(self, fresh);
+        self.display = old.display;
+        self.deterministic = old.deterministic;
+        self.seed = old.seed;
+        self.active_seed = self.deterministic.then_some(self.seed);
+        self.speed_governor = old.speed_governor;
+        self.replay_status = old.replay_status;
+        self.step_budget_ms = old.step_budget_ms;
+        self.pinned = old.pinned;
+        self.reference = old.reference;
+        self.reference_align_peaks = old.reference_align_peaks;
+        self.dot_texture = old.dot_texture;
+        self.confirm_reset = old.confirm_reset;
+        self.pause_at_herd_immunity = old.pause_at_herd_immunity;
+        self.pause_at_first_death = old.pause_at_first_death;
+        self.nudge = old.nudge;
+        self.ensemble_runs = old.ensemble_runs;
+        self.ensemble_threads = old.ensemble_threads;
+        self.ensemble_job = old.ensemble_job;
+        self.ensemble = old.ensemble;
+        self.sensitivity_measure = old.sensitivity_measure;
+        self.sensitivity_perturbation = old.sensitivity_perturbation;
+        self.sensitivity_job = old.sensitivity_job;
+        self.sensitivity = old.sensitivity;
+        self.checkpoint_interval_days = old.checkpoint_interval_days;
+        self.max_checkpoints = old.max_checkpoints;
+        self.reporting_fraction = old.reporting_fraction;
+        self.reporting_noise = old.reporting_noise;
+        self.title = old.title;
+        self.notes = old.notes;
+        self.export_interval_days = old.export_interval_days;
+        self.compared_configs = old.compared_configs;
+        self.tag_mode = old.tag_mode;
+        self.filmstrip_interval_days = old.filmstrip_interval_days;
+        self.filmstrip_pause = old.filmstrip_pause;
+        self.record_every_n_frames = old.record_every_n_frames;
+        self.paused = true;
+    }
+
+    /// Starts a new time series at day zero from the current state, leaving the grid as it is
+    fn restart_stats(&mut self) {
+        // The schedule runs again from the new day zero
+        self.revert_schedule();
+        self.stats.clear();
+        self.time_elapsed = Duration::ZERO;
+        self.last_frame_time = Instant::now();
+        // Those infected now are the new series' seed
+        let infected = self
+            .grid
+            .0
+            .values()
+            .flatten()
+            .filter(|person| matches!(person.state, InfectionState::Infected(_)))
+            .map(|person| person.id);
+        self.line_list = LineList::seeded(infected);
+        self.num_cumulative_infected = self.num_infected;
+        self.num_reinfections = 0;
+        // Contact times and checkpoints are from the old clock
+        self.contacts.clear();
+        self.checkpoints.clear();
+        self.herd_immunity_at = None;
+        self.first_death = None;
+        self.milestones.clear();
+        self.peak_infected = self.num_infected;
+        self.trajectories = Trajectories::default();
+        self.filmstrip = Filmstrip::default();
+        self.reported.clear();
+        self.front = InfectionFront::seeded(
+            &self.grid,
+            self.params.cell_size,
+            self.params.boundary,
+            Duration::ZERO,
+        );
+    }
+
+    /// Colour of each shaded cell for the selected overlay
+    fn cell_overlay(&self) -> Vec<((i32, i32), Color32)> {
+        match self.display.overlay {
+            CellOverlay::None => Vec::new(),
+            CellOverlay::Heat => self
+                .heat
+                .iter()
+                .map(|(&cell, &level)| {
+                    let alpha = (level * 160.0) as u8;
+                    (cell, Color32::from_rgba_unmultiplied(255, 140, 0, alpha))
+                })
+                .collect(),
+            CellOverlay::InfectionCount => {
+                let max = self.infection_counts.values().copied().max().unwrap_or(1);
+                self.infection_counts
+                    .iter()
+                    .map(|(&cell, &count)| {
+                        let alpha = (40.0 + 180.0 * count as f32 / max as f32) as u8;
+                        (cell, Color32::from_rgba_unmultiplied(200, 0, 255, alpha))
+                    })
+                    .collect()
+            }
+            CellOverlay::Contamination => {
+                let max = self
+                    .contamination
+                    .values()
+                    .map(|levels| levels.iter().sum())
+                    .fold(f32::EPSILON, f32::max);
+                self.contamination
+                    .iter()
+                    .map(|(&cell, levels)| {
+                        let alpha = (200.0 * levels.iter().sum::<f32>() / max) as u8;
+                        (cell, Color32::from_rgba_unmultiplied(150, 100, 30, alpha))
+                    })
+                    .collect()
+            }
+            CellOverlay::LocalImmunity => {
+                let threshold = self.display.local_immunity_threshold;
+                self.grid
+                    .neighbourhood_immunity()
+                    .into_iter()
+                    .filter(|&(_, immune)| immune >= threshold)
+                    .map(|(cell, immune)| {
+                        // Deeper green the further past the threshold
+                        let margin = (immune - threshold) / (1.0 - threshold).max(f32::EPSILON);
+                        let alpha = (60.0 + 140.0 * margin) as u8;
+                        (cell, Color32::from_rgba_unmultiplied(0, 200, 80, alpha))
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// The current scenario, as saved alongside exports
+    fn config(&self) -> Config {
+        Config {
+            title: self.title.clone(),
+            notes: self.notes.clone(),
+            init_infected: self.init_infected,
+            total: self.total,
+            params: self.base_params(),
         }
     }
 
@@ -165,393 +792,4960 @@ impl Pandemic {
             };
             if ui.add(Button::new("▶")).clicked() {
                 self.step_speed = 1.0;
-                self.paused = false;
-                self.last_frame_time = Instant::now();
+                self.resume();
             };
             if ui.add(Button::new("▶▶")).clicked() {
                 self.step_speed = 2.0;
-                self.paused = false;
-                self.last_frame_time = Instant::now();
+                self.resume();
             };
             if ui.add(Button::new("▶▶▶")).clicked() {
                 self.step_speed = 4.0;
-                self.paused = false;
-                self.last_frame_time = Instant::now();
+                self.resume();
             };
             if ui.add(Button::new("⟳")).clicked() {
-                let infection_prob = self.infection_prob;
-                let infection_time_s = self.infection_time_s;
-                let death_prob = self.death_prob;
-
-                *self = Self::new(self.init_infected, self.total);
-                self.infection_prob = infection_prob;
-                self.infection_time_s = infection_time_s;
-                self.death_prob = death_prob;
-                self.paused = true;
+                if self.confirm_reset && self.time_elapsed >= RESET_CONFIRM_AFTER {
+                    self.reset_pending = true;
+                } else {
+                    self.reset();
+                }
+            }
+            ui.end_row();
+
+            // Slow motion, for watching individual transmissions
+            for speed in [0.1, 0.25, 0.5] {
+                if ui.add(Button::new(format!("{speed}×"))).clicked() {
+                    self.step_speed = speed;
+                    self.resume();
+                }
+            }
+        });
+        if self.reset_pending {
+            ui.horizontal(|ui| {
+                ui.add(Label::new("Discard this run?"));
+                if ui.add(Button::new("Reset")).clicked() {
+                    self.reset();
+                }
+                if ui.add(Button::new("Cancel")).clicked() {
+                    self.reset_pending = false;
+                }
+            });
+        }
+        ui.checkbox(&mut self.confirm_reset, "Confirm before reset");
+        ComboBox::from_label("Arrow keys adjust")
+            .selected_text(
+                self.nudge
+                    .map_or("Nothing".to_owned(), |nudge| format!("{nudge}")),
+            )
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.nudge, None, "Nothing");
+                for nudge in Nudge::ALL {
+                    ui.selectable_value(&mut self.nudge, Some(nudge), format!("{nudge}"));
+                }
+            })
+            .response
+            .on_hover_text("Up and down nudge the parameter by a small step; hold shift for ten");
+        ui.checkbox(&mut self.deterministic, "Deterministic")
+            .on_hover_text(
+                "Replay the same run on every reset: a fixed seed and a fixed timestep, with \
+                 speeds counting model steps per frame instead of multiplying time",
+            );
+        if self.deterministic {
+            ui.horizontal(|ui| {
+                ui.add(Label::new("Seed"));
+                ui.add(DragValue::new(&mut self.seed));
+            })
+            .response
+            .on_hover_text("Takes effect on the next reset");
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.speed_governor, "Keep steps under");
+                ui.add(
+                    DragValue::new(&mut self.step_budget_ms)
+                        .range(1.0..=100.0)
+                        .suffix(" ms/frame"),
+                );
+            })
+            .response
+            .on_hover_text(
+                "Take fewer steps per frame when taking them all would drop frames, at the cost \
+                 of how far each frame goes depending on the machine",
+            );
+            if ui
+                .add(Button::new("Check reproducibility"))
+                .on_hover_text("Replay this seed twice headlessly and compare the runs")
+                .clicked()
+            {
+                let divergence = ensemble::first_divergence(
+                    self.init_infected,
+                    self.total,
+                    &self.base_params(),
+                    self.seed,
+                );
+                self.replay_status = Some(match divergence {
+                    Some(time) => format!("Runs diverged on day {:.1}", time.as_secs_f32()),
+                    None => "Runs replayed identically".to_owned(),
+                });
+            }
+            if let Some(status) = &self.replay_status {
+                ui.add(Label::new(RichText::new(status).small()));
+            }
+            if self.speed_governor && self.throttle < 0.99 && !self.paused {
+                ui.colored_label(
+                    Palette::of(&self.display).warning,
+                    format!(
+                        "Throttled to {:.0}% speed to stay responsive",
+                        self.throttle * 100.0
+                    ),
+                );
+            }
+        }
+        if ui
+            .add(Button::new("Restart stats from here"))
+            .on_hover_text(
+                "Start a fresh time series from the current state, keeping everyone in place",
+            )
+            .clicked()
+        {
+            self.restart_stats();
+        }
+
+        let can_step_back = self
+            .checkpoints
+            .front()
+            .is_some_and(|oldest| oldest.time() < self.time_elapsed);
+        if ui
+            .add_enabled(can_step_back, Button::new("⏮ Step back"))
+            .on_hover_text("Return to the latest checkpoint before now")
+            .clicked()
+        {
+            self.step_back();
+        }
+        ui.horizontal(|ui| {
+            ui.add(Label::new("Rewind to day"));
+            ui.add(
+                DragValue::new(&mut self.rewind_to_day)
+                    .range(0.0..=self.time_elapsed.as_secs_f32())
+                    .speed(0.1),
+            );
+            if ui.add_enabled(can_step_back, Button::new("Go")).clicked() {
+                self.rewind_to(Duration::from_secs_f32(self.rewind_to_day));
             }
         });
+        ui.horizontal(|ui| {
+            ui.add(Label::new("Checkpoint every"));
+            ui.add(
+                DragValue::new(&mut self.checkpoint_interval_days)
+                    .range(0.5..=100.0)
+                    .speed(0.1)
+                    .suffix(" days"),
+            );
+        });
+        ui.horizontal(|ui| {
+            ui.add(Label::new("Keep at most"));
+            ui.add(DragValue::new(&mut self.max_checkpoints).range(0..=200));
+        })
+        .response
+        .on_hover_text("Each checkpoint holds a full copy of the population; 0 turns them off");
         ui.add_space(15.);
 
         ui.heading("Initial conditions");
-        ui.add(Label::new("Initial infected"));
-        ui.add(Slider::new(&mut self.init_infected, 0..=1000));
-
-        ui.add(Label::new("Total people"));
-        ui.add(Slider::new(&mut self.total, 0..=10000));
-        ui.add_space(15.);
+        param_slider(
+            ui,
+            "Initial infected",
+            &mut self.init_infected,
+            0..=MAX_INIT_INFECTED,
+        );
+        param_slider(
+            ui,
+            "Strain B seeds",
+            &mut self.params.strain_b_seeds,
+            0..=self.init_infected,
+        )
+        .on_hover_text(
+            "How many of the initial infected carry strain B; each strain's seeds start \
+             clustered on opposite sides of the grid",
+        );
+        if self.params.strain_b_seeds > 0 {
+            ui.add(Label::new(format!(
+                "Strain A seeds: {}",
+                self.init_infected
+                    .saturating_sub(self.params.strain_b_seeds)
+            )));
+            param_slider(
+                ui,
+                "Strain B transmissibility ×",
+                &mut self.params.strain_b_transmissibility,
+                0.0..=3.0,
+            );
+        }
+        param_slider(
+            ui,
+            "Introduce infection on day",
+            &mut self.params.seed_day,
+            0.0..=60.0,
+        )
+        .on_hover_text(
+            "Everyone mixes while healthy until this day; 0 seeds the infected from the start",
+        );
+        if self.awaiting_seed
+            && ui
+                .add(Button::new("Introduce infection now"))
+                .on_hover_text("Shortcut: I")
+                .clicked()
+        {
+            self.introduce_infection();
+        }
 
-        ui.heading("Simulation parameters");
-        ui.add(Label::new("Death probability"));
-        ui.add(Slider::new(&mut self.death_prob, 0.0..=1.0));
+        param_slider(ui, "Total people", &mut self.total, 0..=MAX_TOTAL);
+        if self.total > SLOW_TOTAL {
+            ui.colored_label(
+                Palette::of(&self.display).warning,
+                "Populations this large may run slowly",
+            );
+        }
 
-        ui.add(Label::new("Infection probability"));
-        ui.add(Slider::new(&mut self.infection_prob, 0.0..=1.0));
+        param_slider(
+            ui,
+            "Initial placement region",
+            &mut self.params.init_density_region,
+            0.05..=1.0,
+        );
 
-        ui.add(Label::new("Infection time (days)"));
-        ui.add(Slider::new(&mut self.infection_time_s, 0.0..=30.0));
-        ui.add_space(15.);
+        param_slider(
+            ui,
+            "Innately immune fraction",
+            &mut self.params.innate_immune_fraction,
+            0.0..=1.0,
+        )
+        .on_hover_text("Share of the initially healthy who can never be infected, drawn in teal");
 
-        ui.add(Label::new(format!(
-            r#"Healthy: {} individuals
-Infected: {} individuals
-Recovered: {} individuals
-Dead: {} individuals
-Current time: {:.1} days"#,
-            self.num_healthy,
-            self.num_infected,
-            self.num_recovered,
-            self.num_dead,
-            self.time_elapsed.as_secs_f32()
-        )));
-    }
+        ui.checkbox(&mut self.params.spread_placement, "Spread out placement")
+            .on_hover_text("Keep people at least the minimum spacing apart where possible");
+        if self.params.spread_placement {
+            param_slider(
+                ui,
+                "Minimum spacing",
+                &mut self.params.min_spacing,
+                0.0..=5.0,
+            );
+        }
 
-    fn graph_ui(&mut self, ui: &mut Ui) {
-        ui.vertical(|ui| {
-            // Graph selector
-            ComboBox::from_id_salt("graph_display")
-                .selected_text(format!("{}", self.graph))
-                .show_ui(ui, |ui| {
-                    ui.selectable_value(
-                        &mut self.graph,
-                        GraphOptions::Healthy,
-                        format!("{}", GraphOptions::Healthy),
-                    );
-                    ui.selectable_value(
-                        &mut self.graph,
-                        GraphOptions::Infected,
-                        format!("{}", GraphOptions::Infected),
-                    );
+        ComboBox::from_label("Initial headings")
+            .selected_text(format!("{}", self.params.init_heading))
+            .show_ui(ui, |ui| {
+                for heading in HeadingDistribution::ALL {
                     ui.selectable_value(
-                        &mut self.graph,
-                        GraphOptions::Recovered,
-                        format!("{}", GraphOptions::Recovered),
+                        &mut self.params.init_heading,
+                        heading,
+                        format!("{heading}"),
                     );
+                }
+            });
+        if self.params.init_heading == HeadingDistribution::Aligned {
+            param_slider(
+                ui,
+                "Flow direction (degrees)",
+                &mut self.params.flow_angle_deg,
+                0.0..=360.0,
+            );
+        }
+        ui.add_space(15.);
+
+        ui.heading("Simulation parameters");
+        ComboBox::from_label("Simulation")
+            .selected_text(format!("{}", self.params.mode))
+            .show_ui(ui, |ui| {
+                for mode in SimulationMode::ALL {
+                    ui.selectable_value(&mut self.params.mode, mode, format!("{mode}"))
+                        .on_hover_text(mode.description());
+                }
+            })
+            .response
+            .on_hover_text("Takes effect on the next reset");
+        if self.params.mode == SimulationMode::Field {
+            ui.add(Label::new("Field population"));
+            ui.add(
+                Slider::new(&mut self.params.field_population, 1000..=100_000_000)
+                    .logarithmic(true),
+            )
+            .on_hover_text("Takes effect on the next reset");
+            param_slider(
+                ui,
+                "Field diffusion (per day)",
+                &mut self.params.field_diffusion,
+                0.0..=2.0,
+            );
+        }
+        ComboBox::from_label("Transmission model")
+            .selected_text(format!("{}", self.params.transmission_model))
+            .show_ui(ui, |ui| {
+                for model in TransmissionModel::ALL {
                     ui.selectable_value(
-                        &mut self.graph,
-                        GraphOptions::Dead,
-                        format!("{}", GraphOptions::Dead),
-                    );
-                });
-            ui.add_space(20.);
+                        &mut self.params.transmission_model,
+                        model,
+                        format!("{model}"),
+                    )
+                    .on_hover_text(model.description());
+                }
+            });
+        if self.params.transmission_model == TransmissionModel::NearestHealthy {
+            param_slider(
+                ui,
+                "Spread radius",
+                &mut self.params.spread_radius,
+                0.1..=10.0,
+            );
+        }
+        param_slider(
+            ui,
+            "Death probability",
+            &mut self.params.death_prob,
+            PROBABILITY_RANGE,
+        );
 
-            macro_rules! map_stats {
-                ($field:ident) => {
-                    self.stats
-                        .iter()
-                        .map(|stat| (stat.time, stat.$field))
-                        .collect()
-                };
+        let unit = self.params.infection_prob_unit;
+        param_slider(
+            ui,
+            "Infection probability",
+            &mut self.params.infection_prob,
+            PROBABILITY_RANGE,
+        )
+        .on_hover_text(unit.description());
+        ComboBox::from_label("Infection probability is")
+            .selected_text(format!("{unit}"))
+            .show_ui(ui, |ui| {
+                for unit in InfectionProbUnit::ALL {
+                    ui.selectable_value(
+                        &mut self.params.infection_prob_unit,
+                        unit,
+                        format!("{unit}"),
+                    )
+                    .on_hover_text(unit.description());
+                }
+            });
+        // Both act on cell-mates by chance, which spreading to the nearest doesn't
+        let probabilistic = self.params.transmission_model == TransmissionModel::Probabilistic;
+        ui.add_enabled_ui(probabilistic, |ui| {
+            ui.checkbox(&mut self.params.dose_model, "Dose model")
+                .on_hover_text(
+                    "Infect once enough dose builds up from infectious cell-mates, at the infection \
+                 probability per cell-mate per unit of it, instead of by chance each frame",
+                );
+            if self.params.dose_model {
+                param_slider(
+                    ui,
+                    "Dose threshold",
+                    &mut self.params.dose_threshold,
+                    0.05..=5.0,
+                );
+                param_slider(
+                    ui,
+                    "Dose decay (per day)",
+                    &mut self.params.dose_decay,
+                    0.0..=1.0,
+                )
+                .on_hover_text("Fraction of the built-up dose cleared each day away from infection");
+            }
+            ui.checkbox(
+                &mut self.params.environmental,
+                "Environmental transmission",
+            )
+            .on_hover_text(
+                "Infectious people contaminate their cell, which can infect people who pass through \
+                 it later",
+            );
+            if self.params.environmental {
+                param_slider(
+                    ui,
+                    "Contamination deposited (per day)",
+                    &mut self.params.contamination_deposit,
+                    0.0..=5.0,
+                )
+                .on_hover_text("Added to a cell each day for each infectious person in it");
+                param_slider(
+                    ui,
+                    "Contamination decay (per day)",
+                    &mut self.params.contamination_decay,
+                    0.0..=1.0,
+                );
+                param_slider(
+                    ui,
+                    "Environmental infection rate",
+                    &mut self.params.environmental_infection_rate,
+                    0.0..=1.0,
+                )
+                .on_hover_text("Chance of infection per day in a cell per unit of contamination");
             }
+        })
+        .response
+        .on_disabled_hover_text("Only under probabilistic transmission");
 
-            let (times, stats): (Vec<Duration>, Vec<usize>) = match self.graph {
-                GraphOptions::Healthy => map_stats!(num_healthy),
-                GraphOptions::Infected => map_stats!(num_infected),
-                GraphOptions::Recovered => map_stats!(num_recovered),
-                GraphOptions::Dead => map_stats!(num_dead),
-            };
+        param_slider(
+            ui,
+            "Infection time (days)",
+            &mut self.params.infection_time_s,
+            INFECTION_TIME_RANGE,
+        );
+        param_slider(
+            ui,
+            "Infection time spread",
+            &mut self.params.duration_variability,
+            0.0..=1.0,
+        )
+        .on_hover_text("Standard deviation of each person's infection time, relative to the mean");
+        param_slider(
+            ui,
+            "Natural immunity (days)",
+            &mut self.params.natural_immunity_days,
+            0.0..=365.0,
+        )
+        .on_hover_text("How long recovery protects against reinfection; 0 for life");
 
-            if let [.., max_time] = times[..] {
-                let max_time = max_time.as_millis();
-                let num_individuals =
-                    self.num_healthy + self.num_infected + self.num_recovered + self.num_dead;
+        log_param_slider(
+            ui,
+            "Contact cell size",
+            &mut self.params.cell_size,
+            CELL_SIZE_RANGE,
+        );
 
-                let painter = ui.painter();
-                let rect = ui.available_rect_before_wrap();
-                let min = rect.min;
-                let max = rect.max;
+        param_slider(
+            ui,
+            "Max people per cell",
+            &mut self.params.max_per_cell,
+            0..=20,
+        )
+        .on_hover_text("People turn back rather than enter a full cell; 0 for no limit");
 
-                let x_axis_text = painter.layout_no_wrap(
-                    self.graph.to_string(),
-                    FontId::default(),
-                    Color32::GRAY,
-                );
-                let _x_axis = painter.add(TextShape::new(
-                    Pos2 {
-                        x: min.x + rect.width() / 2.0,
-                        y: max.y,
-                    },
-                    x_axis_text.clone(),
-                    Color32::GRAY,
-                ));
-                let y_axis_text =
-                    painter.layout_no_wrap("time".to_owned(), FontId::default(), Color32::GRAY);
-                let _y_axis = painter.add(
-                    TextShape::new(
-                        Pos2 {
-                            x: min.x,
-                            y: min.y + rect.height() / 2.0,
-                        },
-                        y_axis_text.clone(),
-                        Color32::GRAY,
-                    )
-                    .with_angle(1.5 * PI),
-                );
+        ui.checkbox(&mut self.params.collisions, "Collisions")
+            .on_hover_text("People sharing a cell scatter in random directions when they meet");
+        if self.params.collisions {
+            param_slider(
+                ui,
+                "Collision distance",
+                &mut self.params.collision_radius,
+                0.05..=2.0,
+            );
+        }
 
-                let mut x_offset = min.x + y_axis_text.rect.max.x + 5.0;
-                let mut y_offset = max.y + x_axis_text.rect.min.y - 5.0;
-                let _x_axis = painter.add(Shape::LineSegment {
-                    points: [
-                        Pos2 {
-                            x: x_offset,
-                            y: y_offset,
-                        },
-                        Pos2 {
-                            x: max.x,
-                            y: y_offset,
-                        },
-                    ],
-                    stroke: Stroke::new(1.0, Color32::GRAY),
-                });
-                let _y_axis = painter.add(Shape::LineSegment {
-                    points: [
-                        Pos2 {
-                            x: x_offset,
-                            y: min.y + 5.0,
-                        },
-                        Pos2 {
-                            x: x_offset,
-                            y: y_offset,
-                        },
-                    ],
-                    stroke: Stroke::new(1.0, Color32::GRAY),
-                });
-                x_offset += 1.5;
-                y_offset -= 1.5;
-                let (w, h) = (max.x - x_offset - 4.0, y_offset - min.y - 4.0);
-
-                let points = times.into_iter().zip(stats.into_iter()).map(|(t, s)| {
-                    let x = t.as_millis() as f32 / max_time as f32;
-                    let y = s as f32 / num_individuals as f32;
-                    Shape::Circle(CircleShape {
-                        center: Pos2 {
-                            x: x_offset + x * w,
-                            y: y_offset - y * h,
-                        },
-                        radius: 2.0,
-                        fill: Color32::GRAY,
-                        stroke: Stroke::NONE,
-                    })
+        ComboBox::from_label("Boundary")
+            .selected_text(format!("{}", self.params.boundary))
+            .show_ui(ui, |ui| {
+                for boundary in Boundary::ALL {
+                    ui.selectable_value(&mut self.params.boundary, boundary, format!("{boundary}"));
+                }
+            });
+
+        ui.add(Label::new(format!(
+            "Gathering points: {}",
+            self.params.attractors.len()
+        )));
+        ui.horizontal(|ui| {
+            if ui.add(Button::new("Add")).clicked() {
+                self.params.attractors.push(Pos2 {
+                    x: random_range(0.0..X_MAX_FLOAT),
+                    y: random_range(0.0..Y_MAX_FLOAT),
                 });
-                painter.extend(points);
+            }
+            if ui.add(Button::new("Clear")).clicked() {
+                self.params.attractors.clear();
             }
         });
-    }
+        param_slider(
+            ui,
+            "Gathering pull (per day)",
+            &mut self.params.attraction_strength,
+            0.0..=10.0,
+        );
+        param_slider(
+            ui,
+            "Home range pull",
+            &mut self.params.home_range_strength,
+            0.0..=2.0,
+        )
+        .on_hover_text(
+            "How strongly people turn back towards where they started, growing with their \
+             distance from it; keeps each person within a home range instead of wandering off",
+        );
 
-    fn step(&mut self) {
-        // Amount of motion per ms
-        const MOVE_AMOUNT: f32 = 0.01;
+        param_slider(
+            ui,
+            "Drift (units per day)",
+            &mut self.params.drift_strength,
+            0.0..=10.0,
+        );
+        param_slider(
+            ui,
+            "Drift direction (degrees)",
+            &mut self.params.drift_angle_deg,
+            0.0..=360.0,
+        );
 
-        let elapsed = self.last_frame_time.elapsed();
-        let frame_time = elapsed.as_millis() as f32 * self.step_speed;
-        self.time_elapsed += elapsed;
-        self.last_frame_time = Instant::now();
+        for (label, speed) in [
+            ("Healthy speed ×", &mut self.params.healthy_speed),
+            ("Infected speed ×", &mut self.params.infected_speed),
+        ] {
+            param_slider(ui, label, speed, SPEED_RANGE);
+        }
+        param_slider(
+            ui,
+            "Recovered speed ×",
+            &mut self.params.recovered_speed,
+            0.0..=3.0,
+        )
+        .on_hover_text(
+            "Above 1, recovered people mix more freely because they feel safe (risk \
+             compensation), bringing the still susceptible into contact more often",
+        );
 
-        let infection_time = self.infection_time_s * 1000.0;
-        let survival_prob = 1.0 - self.death_prob;
-        let survive_this_frame = survival_prob.powf(frame_time / infection_time) as f64;
-        let non_infection_prob = 1.0 - self.infection_prob;
-        // Somewhat bastardized estimation
-        let not_infected_this_frame = non_infection_prob.powf(frame_time * MOVE_AMOUNT) as f64;
+        let r0 = ode::Rates::new(&self.params, self.total).r0();
+        ui.add(Label::new(format!("Estimated R₀: {r0:.2}")));
+        if r0 < 1.0 {
+            ui.colored_label(
+                Palette::of(&self.display).warning,
+                "R₀ is below 1, so the epidemic is likely to fizzle out",
+            );
+        } else {
+            ui.add(Label::new(format!(
+                "Herd immunity threshold: {:.0}%",
+                (1.0 - 1.0 / r0) * 100.0
+            )));
+        }
+        match self.herd_immunity_at {
+            Some(time) => ui.add(Label::new(format!(
+                "Herd immunity reached on day {:.1}",
+                time.as_secs_f32()
+            ))),
+            None => ui.add(Label::new(format!(
+                "Immune: {:.0}%",
+                self.immune_fraction() * 100.0
+            ))),
+        };
+        ui.checkbox(&mut self.pause_at_herd_immunity, "Pause at herd immunity");
+        ui.checkbox(&mut self.pause_at_first_death, "Pause at first death")
+            .on_hover_text("Marks where it happened for a few days");
+        ui.add_space(15.);
 
-        let mut people_to_move = Vec::new();
-        // Iterate over rows and cols
-        for ((x_pos, y_pos), people) in self.grid.0.iter_mut() {
-            // Step each individual
-            let dist_to_move = MOVE_AMOUNT * frame_time;
-            people_to_move.extend(people.extract_if(.., |person| {
-                // Step direction
-                let pos = &mut person.pos;
-                let dir = person.direction;
-                let (x_comp, y_comp) = f32::sin_cos(dir);
-                pos.x = pos.x + (dist_to_move * x_comp);
-                pos.y = pos.y + (dist_to_move * y_comp);
-
-                // If OOB, flip direction & reflect back
-                if pos.x < 0.0 {
-                    pos.x = -pos.x;
-                    person.direction = -dir;
-                } else if pos.x > X_MAX_FLOAT {
-                    pos.x = 2.0 * X_MAX_FLOAT - pos.x;
-                    person.direction = -dir;
+        ui.heading("Interventions");
+        ComboBox::from_label("Compliance")
+            .selected_text(format!("{}", self.params.compliance))
+            .show_ui(ui, |ui| {
+                for compliance in ComplianceDistribution::ALL {
+                    ui.selectable_value(
+                        &mut self.params.compliance,
+                        compliance,
+                        format!("{compliance}"),
+                    )
+                    .on_hover_text(compliance.description());
                 }
-                if pos.y < 0.0 {
-                    pos.y = -pos.y;
-                    person.direction = PI - dir;
-                } else if pos.y > Y_MAX_FLOAT {
-                    pos.y = 2.0 * Y_MAX_FLOAT - pos.y;
-                    person.direction = PI - dir;
+            })
+            .response
+            .on_hover_text(
+                "How far each person comes forward for detection, isolates when traced, takes \
+                 up vaccination and keeps to the safe zone; drawn for each person on reset",
+            );
+        if self.params.compliance != ComplianceDistribution::Full {
+            param_slider(
+                ui,
+                "Mean compliance",
+                &mut self.params.mean_compliance,
+                0.0..=1.0,
+            );
+        }
+        param_slider(
+            ui,
+            "Detection probability (per day)",
+            &mut self.params.quarantine_prob,
+            PROBABILITY_RANGE,
+        )
+        .on_hover_text("Detected cases are quarantined until their infection ends");
+        param_slider(
+            ui,
+            "Tracing coverage",
+            &mut self.params.trace_coverage,
+            0.0..=1.0,
+        )
+        .on_hover_text("Share of a detected case's recent cell-mates that are also quarantined");
+        param_slider(
+            ui,
+            "Tracing hold (days)",
+            &mut self.params.trace_hold_days,
+            0.0..=30.0,
+        );
+        param_slider(
+            ui,
+            "Vaccination rate (per day)",
+            &mut self.params.vaccination_rate,
+            PROBABILITY_RANGE,
+        )
+        .on_hover_text("Chance each day that an unvaccinated healthy person is vaccinated");
+        param_slider(
+            ui,
+            "Vaccine immunity (days)",
+            &mut self.params.vaccine_immunity_days,
+            0.0..=365.0,
+        )
+        .on_hover_text("How long vaccine protection lasts before a booster is needed; 0 for life");
+        param_slider(
+            ui,
+            "Hospital beds",
+            &mut self.params.hospital_capacity,
+            0..=200,
+        )
+        .on_hover_text("Shown beside the grid; 0 for no hospital");
+        if self.params.hospital_capacity > 0 {
+            param_slider(
+                ui,
+                "Hospitalization fraction",
+                &mut self.params.hospitalization_fraction,
+                0.0..=1.0,
+            )
+            .on_hover_text(
+                "Share of new cases admitted while beds are free; patients are isolated in their \
+                 bed until their infection ends",
+            );
+        }
+        ui.checkbox(&mut self.params.safe_zone, "Safe zone")
+            .on_hover_text("A shelter that people return home to, where infection is less likely");
+        if self.params.safe_zone {
+            let zone = &mut self.params.safe_zone_rect;
+            let mut corner = zone.min;
+            let mut size = zone.size();
+            ui.horizontal(|ui| {
+                ui.add(Label::new("Corner"));
+                ui.add(DragValue::new(&mut corner.x).range(0.0..=X_MAX_FLOAT));
+                ui.add(DragValue::new(&mut corner.y).range(0.0..=Y_MAX_FLOAT));
+            });
+            ui.horizontal(|ui| {
+                ui.add(Label::new("Size"));
+                ui.add(DragValue::new(&mut size.x).range(1.0..=X_MAX_FLOAT));
+                ui.add(DragValue::new(&mut size.y).range(1.0..=Y_MAX_FLOAT));
+            });
+            *zone = Rect::from_min_size(corner, size);
+            ui.add_enabled_ui(probabilistic, |ui| {
+                param_slider(
+                    ui,
+                    "Zone protection",
+                    &mut self.params.safe_zone_protection,
+                    0.0..=1.0,
+                )
+                .on_hover_text("How much less likely infection is inside the zone; 1 prevents it")
+                .on_disabled_hover_text("Only under probabilistic transmission");
+            });
+            param_slider(
+                ui,
+                "Return home (per day)",
+                &mut self.params.home_return_prob,
+                0.0..=1.0,
+            );
+        }
+        ui.add_space(15.);
+
+        ui.heading("Schedule");
+        let mut removed = None;
+        for (i, (day, change)) in self.params.schedule.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                ui.add(
+                    DragValue::new(day)
+                        .range(0.0..=365.0)
+                        .speed(0.5)
+                        .prefix("Day "),
+                );
+                ComboBox::from_id_salt(("schedule", i))
+                    .width(120.)
+                    .selected_text(format!("{}", change.param))
+                    .show_ui(ui, |ui| {
+                        for param in Nudge::ALL {
+                            ui.selectable_value(&mut change.param, param, format!("{param}"));
+                        }
+                    });
+                // Only the parameter's step and range are wanted, not its current value
+                let (_, step, range) = change.param.target(&mut Params::default());
+                ui.add(DragValue::new(&mut change.value).range(range).speed(step));
+                if ui.add(Button::new("✖")).clicked() {
+                    removed = Some(i);
                 }
+            });
+        }
+        if let Some(i) = removed {
+            self.params.schedule.remove(i);
+        }
+        if ui
+            .add(Button::new("Add change"))
+            .on_hover_text("Set a parameter to a new value when the run reaches a given day")
+            .clicked()
+        {
+            let param = Nudge::InfectionProb;
+            let value = *param.target(&mut self.params).0;
+            let day = self.time_elapsed.as_secs_f32().ceil() + 10.0;
+            self.params
+                .schedule
+                .push((day, ParamChange { param, value }));
+        }
+        ui.add_space(15.);
 
-                if let InfectionState::Infected(t) = person.state {
-                    // Chance to die
-                    let died = random_bool(1.0 - survive_this_frame);
-                    if died {
-                        person.state = InfectionState::Dead;
-                        self.num_infected -= 1;
-                        self.num_dead += 1;
-                        return true;
+        ui.heading("Reporting");
+        param_slider(
+            ui,
+            "Reported fraction",
+            &mut self.reporting_fraction,
+            0.0..=1.0,
+        )
+        .on_hover_text("Share of infections that are detected and reported");
+        param_slider(ui, "Reporting noise", &mut self.reporting_noise, 0.0..=1.0)
+            .on_hover_text("Day-to-day scatter of reports, relative to the expected count");
+        ui.add(Label::new(
+            RichText::new("Applies to reports from now on; shown on the Infected graph").small(),
+        ));
+        ui.add_space(15.);
+
+        ui.heading("Display");
+        ComboBox::from_label("Cell overlay")
+            .selected_text(format!("{}", self.display.overlay))
+            .show_ui(ui, |ui| {
+                for overlay in CellOverlay::ALL {
+                    ui.selectable_value(&mut self.display.overlay, overlay, format!("{overlay}"));
+                }
+            });
+        if self.display.overlay == CellOverlay::LocalImmunity {
+            param_slider(
+                ui,
+                "Protection threshold",
+                &mut self.display.local_immunity_threshold,
+                0.0..=1.0,
+            )
+            .on_hover_text(
+                "Recovered fraction of the people in a cell and the eight around it above which \
+                 the cell is shaded as protected",
+            );
+        }
+        ui.horizontal(|ui| {
+            ui.add(Label::new("Theme"));
+            ui.selectable_value(&mut self.display.theme, Theme::Dark, "Dark");
+            ui.selectable_value(&mut self.display.theme, Theme::Light, "Light");
+        });
+        ui.checkbox(
+            &mut self.display.color_blind_safe,
+            "Color-blind safe colors",
+        )
+        .on_hover_text("Blue for healthy and orange for infected, instead of green and red");
+        ui.checkbox(&mut self.display.infected_diamonds, "Infected as diamonds")
+            .on_hover_text("Tell the infected apart by shape as well as color");
+        ui.horizontal(|ui| {
+            ui.add(Label::new("Show"));
+            ui.checkbox(&mut self.display.show_healthy, "Healthy");
+            ui.checkbox(&mut self.display.show_infected, "Infected");
+            ui.checkbox(&mut self.display.show_recovered, "Recovered");
+        });
+        ui.checkbox(&mut self.display.fade_deaths, "Fade out deaths");
+        ui.checkbox(&mut self.display.show_headings, "Heading vectors")
+            .on_hover_text("Best with small populations");
+        ui.checkbox(&mut self.display.show_profiler, "Step profiler");
+        ui.checkbox(&mut self.display.show_day_counter, "Day counter");
+        ui.checkbox(&mut self.display.show_origins, "Outbreak origin");
+        ui.checkbox(&mut self.display.milestone_pings, "Milestone pings")
+            .on_hover_text("Flash counters at the first death, the infection peak and the end");
+        ui.checkbox(&mut self.display.subgroup_shapes, "Subgroup shapes")
+            .on_hover_text("Square for the vaccinated, triangle for the innately immune");
+        ui.checkbox(&mut self.display.generation_colors, "Color by generation")
+            .on_hover_text(
+                "Infected shade from red for seed cases to yellow for the latest generation",
+            );
+        ui.checkbox(&mut self.display.show_contacts, "Contacts while paused")
+            .on_hover_text(
+                "Link people sharing a cell, who can infect each other; links that could carry \
+                 the infection are tinted",
+            );
+        ui.checkbox(&mut self.display.show_daily_table, "Daily numbers table");
+        ui.checkbox(&mut self.display.dot_mesh, "Batched dot rendering")
+            .on_hover_text("Much faster with large populations");
+        param_slider(ui, "Target FPS", &mut self.display.target_fps, 5.0..=120.0);
+        #[cfg(target_arch = "wasm32")]
+        ui.checkbox(
+            &mut self.display.pause_when_hidden,
+            "Pause while the tab is hidden",
+        );
+        ui.add_space(15.);
+
+        ui.heading("Export");
+        ui.horizontal(|ui| {
+            ui.add(Label::new("Record stats every"));
+            ui.add(
+                DragValue::new(&mut self.record_every_n_frames)
+                    .range(1..=100)
+                    .suffix(" frames"),
+            );
+        })
+        .response
+        .on_hover_text("Recording less often saves memory on long runs");
+        ui.add(Label::new("Title"));
+        ui.text_edit_singleline(&mut self.title);
+        ui.add(Label::new("Notes"));
+        ui.text_edit_multiline(&mut self.notes);
+        if ui.add(Button::new("Save config (JSON)")).clicked() {
+            let json = self.config().to_json();
+            self.export_status = Some(export::save_file("pandemic_config.json", json.as_bytes()));
+        }
+
+        ui.horizontal(|ui| {
+            ui.add(Label::new("Sample every"));
+            ui.add(
+                DragValue::new(&mut self.export_interval_days)
+                    .range(0.01..=30.0)
+                    .speed(0.1)
+                    .suffix(" days"),
+            );
+        });
+        if ui.add(Button::new("Export time series (CSV)")).clicked() {
+            let csv = export::time_series_csv(
+                &self.stats,
+                Duration::from_secs_f32(self.export_interval_days),
+                &self.title,
+                &self.notes,
+            );
+            self.export_status = Some(export::save_file(
+                "pandemic_time_series.csv",
+                csv.as_bytes(),
+            ));
+        }
+        if ui
+            .add(Button::new("Export line list (CSV)"))
+            .on_hover_text("One row per infection, with its outcome")
+            .clicked()
+        {
+            let csv = self.line_list.to_csv(&self.title, &self.notes);
+            self.export_status = Some(export::save_file("pandemic_line_list.csv", csv.as_bytes()));
+        }
+        if ui
+            .add(Button::new("Export arrival map (PNG)"))
+            .on_hover_text("One pixel per cell, brighter where infection arrived earlier")
+            .clicked()
+        {
+            let png = export::arrival_map_png(&self.front.arrivals, self.params.cell_size);
+            self.export_status = Some(export::save_file("pandemic_arrival_map.png", &png));
+        }
+        ui.checkbox(&mut self.tag_mode, "Tag for tracking")
+            .on_hover_text("Click people on the grid to tag or untag them");
+        ui.horizontal(|ui| {
+            ui.add(Label::new(format!(
+                "Tagged: {} ({} samples)",
+                self.tagged.len(),
+                self.trajectories.len()
+            )));
+            if ui.add(Button::new("Clear")).clicked() {
+                self.tagged.clear();
+            }
+        });
+        if ui
+            .add(Button::new("Export trajectories (CSV)"))
+            .on_hover_text("Tagged people's positions and states, each time stats are recorded")
+            .clicked()
+        {
+            let csv = self.trajectories.to_csv(&self.title, &self.notes);
+            self.export_status = Some(export::save_file(
+                "pandemic_trajectories.csv",
+                csv.as_bytes(),
+            ));
+        }
+        self.filmstrip_ui(ui);
+        if let Some(status) = &self.export_status {
+            ui.add(Label::new(RichText::new(status).small()));
+        }
+        ui.add(Label::new(
+            RichText::new("Drop two saved configs onto the window to compare them").small(),
+        ));
+        ui.add_space(15.);
+
+        ui.heading("Ensemble");
+        param_slider(ui, "Headless runs", &mut self.ensemble_runs, 1..=200);
+        #[cfg(not(target_arch = "wasm32"))]
+        ui.horizontal(|ui| {
+            ui.add(Label::new("Threads"));
+            ui.add(DragValue::new(&mut self.ensemble_threads).range(1..=64));
+        });
+        if let Some(job) = &mut self.ensemble_job {
+            if job_progress(ui, job.progress()) {
+                self.ensemble_job = None;
+            } else if let Some(results) = job.poll() {
+                self.ensemble = results.into_iter().next();
+                self.ensemble_job = None;
+                if !matches!(
+                    self.graph,
+                    GraphOptions::ExtinctionTime | GraphOptions::DeathToll
+                ) {
+                    self.graph = GraphOptions::ExtinctionTime;
+                }
+            } else {
+                // Keep the bar moving while paused, when nothing else repaints
+                ui.ctx().request_repaint();
+            }
+        } else if ui.add(Button::new("Run ensemble")).clicked() {
+            self.ensemble_job = Some(EnsembleJob::start(
+                self.ensemble_runs,
+                self.ensemble_threads,
+                self.init_infected,
+                self.total,
+                &self.base_params(),
+            ));
+        }
+        if let Some(ensemble) = &self.ensemble {
+            ui.add(Label::new(format!(
+                "Mean duration: {:.1} days\nMean deaths: {:.1}\nMean peak: {:.1} infected on \
+                 day {:.1}\nMean final size: {:.1}%",
+                ensemble.mean(|run| run.duration_days),
+                ensemble.mean(|run| run.deaths as f32),
+                ensemble.mean(|run| run.peak_infected as f32),
+                ensemble.mean(|run| run.peak_day),
+                ensemble.mean(|run| run.final_size) * 100.0,
+            )));
+        }
+
+        param_slider(
+            ui,
+            "Sensitivity perturbation",
+            &mut self.sensitivity_perturbation,
+            0.01..=0.5,
+        );
+        ComboBox::from_label("Measure")
+            .selected_text(format!("{}", self.sensitivity_measure))
+            .show_ui(ui, |ui| {
+                for measure in Measure::ALL {
+                    ui.selectable_value(
+                        &mut self.sensitivity_measure,
+                        measure,
+                        format!("{measure}"),
+                    );
+                }
+            });
+        if let Some(job) = &mut self.sensitivity_job {
+            if job_progress(ui, job.progress()) {
+                self.sensitivity_job = None;
+            } else if let Some(sensitivity) = job.poll() {
+                self.sensitivity = Some(sensitivity);
+                self.sensitivity_job = None;
+                self.graph = GraphOptions::Sensitivity;
+            } else {
+                ui.ctx().request_repaint();
+            }
+        } else if ui
+            .add(Button::new("Run sensitivity analysis"))
+            .on_hover_text("Runs an ensemble with each parameter scaled down and up")
+            .clicked()
+        {
+            self.sensitivity_job = Some(SensitivityJob::start(
+                self.ensemble_runs,
+                self.ensemble_threads,
+                self.init_infected,
+                self.total,
+                &self.base_params(),
+                self.sensitivity_measure,
+                self.sensitivity_perturbation,
+            ));
+        }
+        ui.add_space(15.);
+
+        let palette = Palette::of(&self.display);
+        let pinged = |text: RichText, milestone| {
+            let ping = self.ping(milestone);
+            if ping > 0.0 {
+                text.background_color(palette.attractor.gamma_multiply(ping * 0.6))
+            } else {
+                text
+            }
+        };
+        if self.outbreak_over() {
+            ui.heading(pinged(RichText::new("Outbreak over"), Milestone::End));
+            let population = self.population().max(1) as f32;
+            ui.add(Label::new(
+                RichText::new(format!(
+                    "Final size: {:.1}% ever infected",
+                    self.num_ever_infected() as f32 / population * 100.0
+                ))
+                .strong(),
+            ))
+            .on_hover_text("Share of the initial population infected at some point");
+            // Reversed so ties go to the earliest peak
+            if let Some(peak) = self.stats.iter().rev().max_by_key(|stat| stat.num_infected) {
+                ui.add(Label::new(format!(
+                    "Peak: {} infected on day {:.1}",
+                    peak.num_infected,
+                    peak.time.as_secs_f32()
+                )));
+            }
+            ui.add(Label::new(format!(
+                "Death toll: {} ({:.1}%)",
+                self.num_dead,
+                self.num_dead as f32 / population * 100.0
+            )));
+            ui.add_space(10.);
+        }
+        self.composition_bar(ui);
+        // One label per counter, so each can be pinged on its own
+        ui.vertical(|ui| {
+            ui.spacing_mut().item_spacing.y = 0.0;
+            for (label, count, milestone) in [
+                ("Healthy", self.num_healthy, None),
+                ("Infected", self.num_infected, Some(Milestone::Peak)),
+                ("Recovered", self.num_recovered, None),
+                ("Dead", self.num_dead, Some(Milestone::FirstDeath)),
+                ("Vaccinated", self.num_vaccinated, None),
+                ("Innately immune", self.num_innate_immune, None),
+                ("Quarantined", self.num_quarantined(), None),
+            ] {
+                let text = RichText::new(format!("{label}: {count} individuals"));
+                ui.add(Label::new(match milestone {
+                    Some(milestone) => pinged(text, milestone),
+                    None => text,
+                }));
+            }
+            if self.params.hospital_capacity > 0 {
+                ui.add(Label::new(format!(
+                    "Hospitalized: {} of {} beds",
+                    self.num_hospitalized(),
+                    self.params.hospital_capacity
+                )));
+            }
+            if self.num_reinfections > 0 {
+                ui.add(Label::new(format!(
+                    "Reinfections: {} of {} infections",
+                    self.num_reinfections, self.num_cumulative_infected
+                )));
+            }
+            if self.params.strain_b_seeds > 0 {
+                ui.add(Label::new(format!(
+                    "Infected by strain: A {}, B {}",
+                    self.num_infected_with(Strain::A),
+                    self.num_infected_with(Strain::B)
+                )));
+            }
+            ui.add(Label::new(format!(
+                "New infections (last day): {}\nCurrent time: {:.1} days",
+                self.daily_incidence(),
+                self.time_elapsed.as_secs_f32()
+            )));
+        });
+        ui.add_space(15.);
+
+        ui.heading("Diagnostics");
+        if let Some(rates) = self.transition_rates() {
+            ui.add(Label::new(
+                RichText::new(format!(
+                    "S→I: {:.2}/day\nI→R: {:.2}/day\nI→D: {:.2}/day",
+                    rates.infections, rates.recoveries, rates.deaths
+                ))
+                .small(),
+            ));
+        }
+        let waves = self.waves();
+        ui.add(Label::new(
+            RichText::new(format!("Waves: {}", waves.len())).small(),
+        ))
+        .on_hover_text("Peaks in the infected curve, each followed by a fall to half of it");
+        for (i, wave) in waves.iter().enumerate() {
+            ui.add(Label::new(
+                RichText::new(format!(
+                    "  {}. day {:.1}, {} infected{}",
+                    i + 1,
+                    wave.time.as_secs_f32(),
+                    wave.peak,
+                    if wave.ongoing { " so far" } else { "" }
+                ))
+                .small(),
+            ));
+        }
+        ui.add(Label::new(
+            RichText::new(format!(
+                "Longest transmission chain: {} generations",
+                self.max_generation
+            ))
+            .small(),
+        ));
+        if let Some(speed) = self.front.speed(self.time_elapsed) {
+            ui.add(Label::new(
+                RichText::new(format!("Front speed: {speed:.2} units/day")).small(),
+            ))
+            .on_hover_text("Growth of the farthest infected cell's distance from the seed");
+        }
+        ui.add_space(15.);
+
+        ui.heading("Run info");
+        let mut seed = self
+            .active_seed
+            .map_or_else(|| "random".to_owned(), |seed| seed.to_string());
+        if self.deterministic && self.active_seed != Some(self.seed) {
+            seed += &format!(" ({} from the next reset)", self.seed);
+        }
+        ui.add(Label::new(
+            RichText::new(format!(
+                "Version: {} ({})\nSeed: {seed}\nParameter hash: {:016x}",
+                env!("CARGO_PKG_VERSION"),
+                env!("PANDEMIC_COMMIT"),
+                self.config().params_hash()
+            ))
+            .small(),
+        ))
+        .on_hover_text(
+            "Deterministic runs with the same version, seed and hash replay identically",
+        );
+    }
+
+    /// Filmstrip settings and export, with a thumbnail of each frame taken
+    fn filmstrip_ui(&mut self, ui: &mut Ui) {
+        const THUMBNAIL_WIDTH: f32 = 96.0;
+
+        param_slider(
+            ui,
+            "Filmstrip every (days)",
+            &mut self.filmstrip_interval_days,
+            0.0..=30.0,
+        )
+        .on_hover_text(format!(
+            "Snapshot the grid this often, up to {} frames; 0 for none",
+            Filmstrip::MAX_FRAMES
+        ));
+        if self.filmstrip_interval_days == 0.0 && self.filmstrip.frames().is_empty() {
+            return;
+        }
+        ui.checkbox(&mut self.filmstrip_pause, "Pause at each frame");
+        if ui
+            .add(Button::new("Export filmstrip (PNG)"))
+            .on_hover_text("Every frame in one image, left to right and top to bottom")
+            .clicked()
+        {
+            let palette = Palette::of(&self.display);
+            let background = match self.display.theme {
+                Theme::Dark => Color32::BLACK,
+                Theme::Light => Color32::WHITE,
+            };
+            let png = self
+                .filmstrip
+                .to_png(background, |state| palette.state(state));
+            self.export_status = Some(export::save_file("pandemic_filmstrip.png", &png));
+        }
+
+        let palette = Palette::of(&self.display);
+        let size = Vec2 {
+            x: THUMBNAIL_WIDTH,
+            y: THUMBNAIL_WIDTH * Y_MAX_FLOAT / X_MAX_FLOAT,
+        };
+        let scale = THUMBNAIL_WIDTH / X_MAX_FLOAT;
+        ScrollArea::horizontal()
+            .id_salt("filmstrip")
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    for frame in self.filmstrip.frames() {
+                        ui.vertical(|ui| {
+                            let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+                            let painter = ui.painter_at(rect);
+                            painter.rect_stroke(
+                                rect,
+                                0.0,
+                                Stroke::new(1.0, palette.foreground),
+                                StrokeKind::Inside,
+                            );
+                            painter.extend(frame.people.iter().map(|&(pos, state)| {
+                                Shape::circle_filled(
+                                    rect.min + pos.to_vec2() * scale,
+                                    1.0,
+                                    palette.state(state),
+                                )
+                            }));
+                            ui.add(Label::new(
+                                RichText::new(format!("Day {:.0}", frame.time.as_secs_f32()))
+                                    .small(),
+                            ));
+                        });
                     }
+                });
+            });
+    }
 
-                    // Update infection time
-                    let new_infection_time = t + frame_time;
-                    person.state = if new_infection_time > infection_time {
-                        self.num_infected -= 1;
-                        self.num_recovered += 1;
-                        InfectionState::Recovered
+    /// The earliest and latest snapshots within the trailing `window` of simulated time
+    fn window(&self, window: Duration) -> Option<(&PandemicSnapshot, &PandemicSnapshot)> {
+        let latest = self.stats.last()?;
+        let since = latest.time.saturating_sub(window);
+        let earliest = &self.stats[self.stats.partition_point(|stat| stat.time < since)];
+        Some((earliest, latest))
+    }
+
+    /// New infections over the last simulated day
+    fn daily_incidence(&self) -> usize {
+        self.window(Duration::from_secs(1))
+            .map_or(0, |(earliest, latest)| {
+                latest.cumulative_infected - earliest.cumulative_infected
+            })
+    }
+
+    /// Average transition flows over the most recent `RATE_WINDOW` of snapshots
+    fn transition_rates(&self) -> Option<TransitionRates> {
+        let (earliest, latest) = self.window(RATE_WINDOW)?;
+        let days = (latest.time - earliest.time).as_secs_f32();
+        if days <= 0.0 {
+            return None;
+        }
+
+        Some(TransitionRates {
+            infections: (latest.cumulative_infected - earliest.cumulative_infected) as f32 / days,
+            recoveries: latest.num_recovered.saturating_sub(earliest.num_recovered) as f32 / days,
+            deaths: latest.num_dead.saturating_sub(earliest.num_dead) as f32 / days,
+        })
+    }
+
+    /// Peaks of the infected curve, oldest first
+    ///
+    /// A wave starts once infections climb past `WAVE_MIN_PEAK` of the population, and ends once
+    /// they fall below `WAVE_TROUGH_RATIO` of its peak. The next can only start once infections
+    /// drop below `WAVE_MIN_PEAK` again, or climb back from a trough by as much as the last wave
+    /// fell to end; the wave still under way, if any, comes last with its highest point so far.
+    fn waves(&self) -> Vec<Wave> {
+        let min_height = (self.population() as f32 * WAVE_MIN_PEAK).max(1.0);
+        let mut waves = Vec::new();
+        let mut rising: Option<Wave> = None;
+        // Lowest count since the last wave ended, while waiting for the trough
+        let mut trough: Option<usize> = None;
+        for stat in &self.stats {
+            let infected = stat.num_infected;
+            if let Some(lowest) = trough {
+                if (infected as f32) < min_height
+                    || infected as f32 * WAVE_TROUGH_RATIO > lowest as f32
+                {
+                    trough = None;
+                } else {
+                    trough = Some(lowest.min(infected));
+                    continue;
+                }
+            }
+            match &mut rising {
+                Some(wave) if infected > wave.peak => {
+                    wave.time = stat.time;
+                    wave.peak = infected;
+                }
+                Some(wave) if (infected as f32) < wave.peak as f32 * WAVE_TROUGH_RATIO => {
+                    waves.extend(rising.take());
+                    trough = Some(infected);
+                }
+                None if infected as f32 >= min_height => {
+                    rising = Some(Wave {
+                        time: stat.time,
+                        peak: infected,
+                        ongoing: false,
+                    });
+                }
+                _ => {}
+            }
+        }
+        waves.extend(rising.map(|wave| Wave {
+            ongoing: true,
+            ..wave
+        }));
+        waves
+    }
+
+    fn graph_ui(&mut self, ui: &mut Ui) {
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.display.dashboard, false, "Single");
+                ui.selectable_value(&mut self.display.dashboard, true, "Dashboard");
+                // Graph selector
+                if !self.display.dashboard {
+                    ComboBox::from_id_salt("graph_display")
+                        .selected_text(format!("{}", self.graph))
+                        .show_ui(ui, |ui| {
+                            for option in GraphOptions::ALL {
+                                ui.selectable_value(&mut self.graph, option, format!("{option}"));
+                            }
+                        });
+                }
+                ui.add(Label::new("Line"));
+                ui.add(
+                    DragValue::new(&mut self.display.line_thickness)
+                        .range(0.5..=6.0)
+                        .speed(0.05)
+                        .suffix(" px"),
+                );
+                ui.checkbox(&mut self.display.show_mean_field, "Well-mixed SIR");
+                ui.checkbox(&mut self.display.day_ticks, "Day ticks");
+                ui.checkbox(&mut self.display.show_reported, "Reported")
+                    .on_hover_text("Daily reported infections, undercounted and noisy");
+                ui.checkbox(&mut self.display.show_projection, "Projection")
+                    .on_hover_text("Well-mixed model fitted to the last few days, run forward");
+                if ui
+                    .add(Button::new("Pin current"))
+                    .on_hover_text("Keep this run's curves as a faint baseline")
+                    .clicked()
+                {
+                    self.pinned = Some(self.stats.clone());
+                }
+                if self.pinned.is_some() && ui.add(Button::new("Unpin")).clicked() {
+                    self.pinned = None;
+                }
+                if ui
+                    .add(Button::new("Load reference data"))
+                    .on_hover_text("Overlay real case counts from a CSV on the Infected graph")
+                    .clicked()
+                {
+                    self.reference_paste = Some(String::new());
+                }
+                if let Some((name, _)) = &self.reference {
+                    ui.checkbox(&mut self.reference_align_peaks, "Align peaks")
+                        .on_hover_text("Stretch the reference in time to peak with the simulation");
+                    if ui
+                        .add(Button::new("Clear reference"))
+                        .on_hover_text(format!("Loaded from {name}"))
+                        .clicked()
+                    {
+                        self.reference = None;
+                    }
+                }
+                if !self.display.dashboard
+                    && !matches!(
+                        self.graph,
+                        GraphOptions::ExtinctionTime
+                            | GraphOptions::DeathToll
+                            | GraphOptions::Sensitivity
+                    )
+                    && ui
+                        .add(Button::new("Export SVG"))
+                        .on_hover_text("Save this graph as a vector figure")
+                        .clicked()
+                {
+                    let svg = self.graph_svg(self.graph);
+                    self.export_status =
+                        Some(export::save_file("pandemic_graph.svg", svg.as_bytes()));
+                }
+            });
+            ui.add_space(20.);
+
+            if self.population() == 0 {
+                ui.add(Label::new("No population to graph"));
+                return;
+            }
+
+            let area = ui.available_rect_before_wrap();
+            if self.display.dashboard {
+                // Small multiples in a 2×2 grid
+                let size = area.size() / 2.0;
+                for (i, graph) in GraphOptions::DASHBOARD.into_iter().enumerate() {
+                    let min = area.min
+                        + Vec2 {
+                            x: (i % 2) as f32 * size.x,
+                            y: (i / 2) as f32 * size.y,
+                        };
+                    let rect = Rect::from_min_size(min, size).shrink(5.0);
+                    self.plot_series(ui.painter(), rect, graph);
+                }
+                return;
+            }
+
+            match self.graph {
+                GraphOptions::ExtinctionTime => {
+                    self.histogram_ui(ui, "days until extinction", |run| run.duration_days);
+                }
+                GraphOptions::DeathToll => {
+                    self.histogram_ui(ui, "deaths", |run| run.deaths as f32);
+                }
+                GraphOptions::Sensitivity => self.sensitivity_ui(ui),
+                graph => self.plot_series(ui.painter(), area, graph),
+            }
+        });
+    }
+
+    /// The `(time, value)` points of a time series graph of `stats`
+    fn series(&self, stats: &[PandemicSnapshot], graph: GraphOptions) -> Vec<(Duration, f32)> {
+        macro_rules! map_stats {
+            ($field:ident) => {
+                stats
+                    .iter()
+                    .map(|stat| (stat.time, stat.$field as f32))
+                    .collect()
+            };
+        }
+
+        match graph {
+            GraphOptions::Healthy => map_stats!(num_healthy),
+            GraphOptions::Infected => map_stats!(num_infected),
+            GraphOptions::Recovered => map_stats!(num_recovered),
+            GraphOptions::Dead => map_stats!(num_dead),
+            GraphOptions::Cumulative => map_stats!(cumulative_infected),
+            GraphOptions::Reinfections => map_stats!(reinfections),
+            GraphOptions::Immune => stats
+                .iter()
+                .map(|stat| {
+                    let living = stat.num_healthy + stat.num_infected + stat.num_recovered;
+                    (stat.time, stat.num_recovered as f32 / living.max(1) as f32)
+                })
+                .collect(),
+            // Infections over the trailing window per infected person, times the infection time
+            GraphOptions::Rt => stats
+                .iter()
+                .filter(|stat| stat.time >= RATE_WINDOW)
+                .map(|stat| {
+                    let start = stat.time - RATE_WINDOW;
+                    let before = &stats[stats.partition_point(|s| s.time < start)];
+                    let days = (stat.time - before.time).as_secs_f32();
+                    let infections = stat
+                        .cumulative_infected
+                        .saturating_sub(before.cumulative_infected)
+                        as f32;
+                    let rt = if stat.num_infected > 0 && days > 0.0 {
+                        infections / days / stat.num_infected as f32 * self.params.infection_time_s
                     } else {
-                        InfectionState::Infected(new_infection_time)
+                        0.0
                     };
+                    (stat.time, rt)
+                })
+                .collect(),
+            GraphOptions::ExtinctionTime | GraphOptions::DeathToll | GraphOptions::Sensitivity => {
+                Vec::new()
+            }
+        }
+    }
+
+    /// Axis ranges and ticks fitting the live `series` and `pinned` baseline of `graph`
+    fn plot_extent(
+        &self,
+        series: &[(Duration, f32)],
+        pinned: &[(Duration, f32)],
+        graph: GraphOptions,
+    ) -> PlotExtent {
+        // Guard against dividing by zero before any time has passed; with no data yet, only the
+        // axes are drawn
+        let max_time = series
+            .last()
+            .map_or(0, |&(time, _)| time.as_millis())
+            .max(1);
+        let pinned_time = pinned.last().map_or(0, |&(time, _)| time.as_millis());
+        // Counts are plotted against the whole population, fractions against one, and Rₜ
+        // against its own range
+        let y_max = match graph {
+            GraphOptions::Immune => 1.0,
+            GraphOptions::Rt => series
+                .iter()
+                .chain(pinned)
+                .map(|&(_, value)| value)
+                .fold(1.0, f32::max),
+            _ => self.population() as f32,
+        };
+        // Make room for the projection beyond the present
+        let time_span = if self.display.show_projection {
+            max_time + PROJECTION.as_millis()
+        } else {
+            max_time
+        }
+        .max(pinned_time);
+
+        let ticks = if self.display.day_ticks {
+            let span_days = time_span as f32 / 1000.0;
+            let spacing = day_tick_spacing(span_days);
+            (0..)
+                .map(|i| i as f32 * spacing)
+                .take_while(|&day| day <= span_days)
+                .map(|day| (day / span_days, format!("Day {day}")))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        PlotExtent {
+            max_time,
+            time_span,
+            y_max,
+            ticks,
+        }
+    }
+
+    /// Enabled model solutions for `graph` up to `max_time` ms, as `(time in ms, value)` lines
+    fn model_lines(&self, graph: GraphOptions, max_time: u128) -> Vec<(Vec<(f32, f32)>, Color32)> {
+        let palette = Palette::of(&self.display);
+        let num_individuals = self.population();
+        let mut lines = Vec::new();
+        // Model solutions start `start_days` in
+        let mut model_line =
+            |solution: Vec<(f64, ode::Compartments)>, start_days: f64, color: Color32| {
+                let line = solution
+                    .iter()
+                    .filter_map(|(days, compartments)| {
+                        let value = graph.model_value(compartments, num_individuals)?;
+                        Some(((start_days + days) as f32 * 1000.0, value as f32))
+                    })
+                    .collect();
+                lines.push((line, color));
+            };
+
+        // Mean-field prediction from the same starting composition
+        if let (true, Some(first)) = (self.display.show_mean_field, self.stats.first()) {
+            let rates = ode::Rates::new(&self.params, num_individuals);
+            let solution = ode::solve(
+                ode::Compartments::from(first),
+                &rates,
+                max_time as f64 / 1000.0,
+            );
+            model_line(solution, 0.0, palette.mean_field);
+        }
+
+        // Forecast from the present, with transmission fitted to the recent past
+        if let (true, Some(last)) = (self.display.show_projection, self.stats.last()) {
+            let fit_from = self.time_elapsed.saturating_sub(PROJECTION_FIT_WINDOW);
+            let recent = &self.stats[self.stats.partition_point(|s| s.time < fit_from)..];
+            if let Some(transmission) = ode::fit_transmission(recent) {
+                let rates = ode::Rates {
+                    transmission,
+                    ..ode::Rates::new(&self.params, num_individuals)
+                };
+                let solution = ode::solve(
+                    ode::Compartments::from(last),
+                    &rates,
+                    PROJECTION.as_secs_f64(),
+                );
+                model_line(solution, last.time.as_secs_f64(), palette.projection);
+            }
+        }
+        lines
+    }
+
+    /// The time series `graph` as an SVG figure, with the same lines as on screen in the light
+    /// palette; wave peaks and the herd immunity marks are left out
+    fn graph_svg(&self, graph: GraphOptions) -> String {
+        let series = self.series(&self.stats, graph);
+        let pinned = self
+            .pinned
+            .as_deref()
+            .map_or_else(Vec::new, |pinned| self.series(pinned, graph));
+        let extent = self.plot_extent(&series, &pinned, graph);
+        let palette = if self.display.color_blind_safe {
+            &Palette::LIGHT_SAFE
+        } else {
+            &Palette::LIGHT
+        };
+        let to_plot =
+            |time_ms: f32, value: f32| (time_ms / extent.time_span as f32, value / extent.y_max);
+        let line = |points: Vec<(Duration, f32)>, color: Color32, stroke: SvgStroke| SvgLine {
+            points: points
+                .into_iter()
+                .map(|(t, value)| to_plot(t.as_millis() as f32, value))
+                .collect(),
+            color,
+            width: self.display.line_thickness,
+            stroke,
+        };
+
+        let mut lines = vec![line(pinned, palette.pinned, SvgStroke::Solid)];
+        if graph == GraphOptions::Infected && self.reference.is_some() {
+            lines.push(SvgLine {
+                points: self
+                    .reference_points()
+                    .into_iter()
+                    .map(|(t, v)| to_plot(t, v))
+                    .collect(),
+                color: palette.reference,
+                width: 1.5,
+                stroke: SvgStroke::Solid,
+            });
+        }
+        lines.push(line(series, palette.series(graph), SvgStroke::Solid));
+        if graph == GraphOptions::Infected && self.display.show_reported {
+            lines.push(SvgLine {
+                width: 2.5,
+                ..line(self.reported.clone(), palette.reported, SvgStroke::Dots)
+            });
+        }
+        for (points, color) in self.model_lines(graph, extent.max_time) {
+            lines.push(SvgLine {
+                points: points.into_iter().map(|(t, v)| to_plot(t, v)).collect(),
+                color,
+                width: 1.5,
+                stroke: SvgStroke::Dashed,
+            });
+        }
+
+        // Rounded, so a fitted Rₜ range doesn't print every digit
+        let y_max = (extent.y_max * 100.0).round() / 100.0;
+        export::plot_svg(
+            "time",
+            &graph.to_string(),
+            &extent.ticks,
+            &[(0.0, "0".to_owned()), (1.0, format!("{y_max}"))],
+            &lines,
+            palette.foreground,
+        )
+    }
+
+    /// Draws the time series `graph` into `rect`, with any model lines enabled for it
+    fn plot_series(&self, painter: &Painter, rect: Rect, graph: GraphOptions) {
+        let series = self.series(&self.stats, graph);
+        let pinned = self
+            .pinned
+            .as_deref()
+            .map_or_else(Vec::new, |pinned| self.series(pinned, graph));
+        let extent = self.plot_extent(&series, &pinned, graph);
+        let palette = Palette::of(&self.display);
+        let Some(plot) = draw_axes(
+            painter,
+            rect,
+            "time",
+            &graph.to_string(),
+            &extent.ticks,
+            palette.foreground,
+        ) else {
+            return;
+        };
+
+        let to_plot = |time_ms: f32, value: f32| Pos2 {
+            x: plot.min.x + time_ms / extent.time_span as f32 * plot.width(),
+            y: plot.max.y - value / extent.y_max * plot.height(),
+        };
+
+        // The pinned baseline goes behind the live series. A lone point has no line through it,
+        // so is marked with a dot
+        let line = |series: Vec<(Duration, f32)>, color: Color32| {
+            let points: Vec<Pos2> = series
+                .into_iter()
+                .map(|(t, value)| to_plot(t.as_millis() as f32, value))
+                .collect();
+            match points[..] {
+                [point] => Shape::circle_filled(point, self.display.line_thickness, color),
+                _ => Shape::line(points, Stroke::new(self.display.line_thickness, color)),
+            }
+        };
+        painter.add(line(pinned, palette.pinned));
+        // Real case counts, scaled to the simulation's peak; cut off at the present
+        if graph == GraphOptions::Infected && self.reference.is_some() {
+            let reference: Vec<Pos2> = self
+                .reference_points()
+                .into_iter()
+                .filter(|&(time_ms, _)| time_ms <= extent.time_span as f32)
+                .map(|(time_ms, value)| to_plot(time_ms, value))
+                .collect();
+            painter.add(Shape::line(reference, Stroke::new(1.5, palette.reference)));
+        }
+        painter.add(line(series, palette.series(graph)));
+
+        // Daily reports, as markers over the true curve
+        if graph == GraphOptions::Infected && self.display.show_reported {
+            for &(time, reported) in &self.reported {
+                let point = to_plot(time.as_millis() as f32, reported);
+                painter.circle_filled(point, 2.5, palette.reported);
+            }
+        }
+
+        // Number each wave's peak
+        if graph == GraphOptions::Infected {
+            for (i, wave) in self.waves().iter().enumerate() {
+                let peak = to_plot(wave.time.as_millis() as f32, wave.peak as f32);
+                painter.circle_stroke(peak, 4.0, Stroke::new(1.5, palette.foreground));
+                painter.text(
+                    peak - Vec2 { x: 0.0, y: 6.0 },
+                    Align2::CENTER_BOTTOM,
+                    format!("{}", i + 1),
+                    FontId::proportional(10.0),
+                    palette.foreground,
+                );
+            }
+        }
+
+        // Herd immunity threshold, and when it was crossed
+        if let (GraphOptions::Immune, Some(threshold)) = (graph, self.herd_immunity_threshold()) {
+            let stroke = Stroke::new(1.0, palette.warning);
+            let y = to_plot(0.0, threshold).y;
+            painter.extend(Shape::dashed_line(
+                &[Pos2 { x: plot.min.x, y }, Pos2 { x: plot.max.x, y }],
+                stroke,
+                6.0,
+                4.0,
+            ));
+            if let Some(time) = self.herd_immunity_at {
+                let x = to_plot(time.as_millis() as f32, 0.0).x;
+                painter.vline(x, plot.y_range(), stroke);
+            }
+        }
+
+        // Scheduled changes made so far, labelled along the top
+        for &(day, change) in &self.params.schedule {
+            if day > self.time_elapsed.as_secs_f32() {
+                continue;
+            }
+            let x = to_plot(day * 1000.0, 0.0).x;
+            painter.extend(Shape::dashed_line(
+                &[Pos2 { x, y: plot.min.y }, Pos2 { x, y: plot.max.y }],
+                Stroke::new(1.0, palette.foreground),
+                3.0,
+                3.0,
+            ));
+            painter.text(
+                Pos2 {
+                    x: x + 3.0,
+                    y: plot.min.y,
+                },
+                Align2::LEFT_TOP,
+                change.to_string(),
+                FontId::proportional(9.0),
+                palette.foreground,
+            );
+        }
+
+        // Model solutions as dashed lines
+        for (line, color) in self.model_lines(graph, extent.max_time) {
+            let line: Vec<Pos2> = line
+                .into_iter()
+                .map(|(t, value)| to_plot(t, value))
+                .collect();
+            painter.extend(Shape::dashed_line(&line, Stroke::new(1.5, color), 6.0, 4.0));
+        }
+    }
+
+    /// A stacked bar splitting the whole population into its compartments, coloured by state
+    fn composition_bar(&self, ui: &mut Ui) {
+        const HEIGHT: f32 = 12.0;
+
+        let palette = Palette::of(&self.display);
+        let (rect, response) = ui.allocate_exact_size(
+            Vec2 {
+                x: ui.available_width(),
+                y: HEIGHT,
+            },
+            egui::Sense::hover(),
+        );
+        let population = self.population().max(1) as f32;
+        let segments = [
+            ("Healthy", self.num_healthy, palette.healthy),
+            ("Infected", self.num_infected, palette.infected),
+            ("Recovered", self.num_recovered, palette.recovered),
+            ("Dead", self.num_dead, palette.dying),
+        ];
+
+        let painter = ui.painter();
+        let mut x = rect.min.x;
+        for (_, count, color) in segments {
+            let width = rect.width() * count as f32 / population;
+            let segment = Rect::from_min_max(
+                Pos2 { x, y: rect.min.y },
+                Pos2 {
+                    x: x + width,
+                    y: rect.max.y,
+                },
+            );
+            painter.rect_filled(segment, 0.0, color);
+            x += width;
+        }
+        response.on_hover_text(
+            segments
+                .map(|(label, count, _)| {
+                    format!("{label}: {:.1}%", count as f32 / population * 100.0)
+                })
+                .join("\n"),
+        );
+    }
+
+    /// Rings the people tagged for tracking on the grid drawn in `grid_rect`, and tags or untags
+    /// whoever is clicked in tag mode
+    fn tagging(&mut self, ui: &Ui, grid_rect: Rect, palette: &Palette) {
+        let scale = grid_rect.size()
+            / Vec2 {
+                x: X_MAX_FLOAT,
+                y: Y_MAX_FLOAT,
+            };
+        if self.tag_mode {
+            let response = ui.interact(grid_rect, ui.id().with("tagging"), egui::Sense::click());
+            if let Some(click) = response.interact_pointer_pos()
+                && response.clicked()
+            {
+                let pos = ((click - grid_rect.min) / scale).to_pos2();
+                let nearest = self
+                    .grid
+                    .0
+                    .values()
+                    .flatten()
+                    .filter(|person| !matches!(person.state, InfectionState::Dying(_)))
+                    .map(|person| (person.id, person.pos.distance(pos)))
+                    .min_by(|a, b| a.1.total_cmp(&b.1));
+                if let Some((id, distance)) = nearest
+                    && distance <= TAG_RADIUS
+                    && !self.tagged.remove(&id)
+                {
+                    self.tagged.insert(id);
                 }
+            }
+        }
 
-                // Do not retain if out of grid element
-                let grid_x = pos.x as i32;
-                let grid_y = pos.y as i32;
-                grid_x != *x_pos || grid_y != *y_pos
-            }));
+        if !self.tagged.is_empty() {
+            let stroke = Stroke::new(2.0, palette.attractor);
+            for person in self.grid.0.values().flatten() {
+                if self.tagged.contains(&person.id) {
+                    let center = grid_rect.min + person.pos.to_vec2() * scale;
+                    ui.painter().circle_stroke(center, 8.0, stroke);
+                }
+            }
+        }
+    }
+
+    /// Loads a config dropped onto the window for comparison, displacing the older of two
+    fn compare_config(&mut self, name: String, contents: Result<Vec<u8>, String>) {
+        match contents.and_then(|json| Config::from_json(&json)) {
+            Ok(config) => {
+                if self.compared_configs.len() == 2 {
+                    self.compared_configs.pop_front();
+                }
+                self.compared_configs.push_back((name, config));
+            }
+            Err(e) => self.export_status = Some(format!("Failed to load {name}: {e}")),
+        }
+    }
+
+    /// Overlays case counts from a CSV file on the Infected graph, replacing any already loaded
+    fn load_reference(&mut self, name: String, contents: Result<Vec<u8>, String>) {
+        match contents.and_then(|csv| export::parse_reference_csv(&String::from_utf8_lossy(&csv))) {
+            Ok(days) => {
+                self.reference = Some((name, days));
+                self.reference_paste = None;
+                self.graph = GraphOptions::Infected;
+            }
+            Err(e) => self.export_status = Some(format!("Failed to load {name}: {e}")),
+        }
+    }
+
+    /// Window for pasting in reference case counts, open while `reference_paste` is set
+    fn reference_loader(&mut self, ctx: &egui::Context) {
+        let Some(csv) = &mut self.reference_paste else {
+            return;
+        };
+        let mut open = true;
+        let mut load = false;
+        egui::Window::new("Reference data")
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.add(Label::new(
+                    "Paste rows of day,cases below, or drop a .csv file onto the window",
+                ));
+                ui.add(
+                    egui::TextEdit::multiline(csv)
+                        .desired_rows(10)
+                        .code_editor(),
+                );
+                // Checked as it's typed, so mistakes show before loading
+                let parsed = export::parse_reference_csv(csv);
+                match &parsed {
+                    Ok(days) => ui.add(Label::new(format!("{} days of case counts", days.len()))),
+                    Err(e) => ui.add(Label::new(RichText::new(e).small())),
+                };
+                load = ui
+                    .add_enabled(parsed.is_ok(), Button::new("Load"))
+                    .clicked();
+            });
+        if load {
+            let csv = self.reference_paste.take().unwrap_or_default();
+            self.load_reference("pasted data".to_owned(), Ok(csv.into_bytes()));
+        } else if !open {
+            self.reference_paste = None;
+        }
+    }
+
+    /// The reference case counts as `(time in ms, value)` points on the Infected graph, scaled
+    /// so their peak matches the simulation's and starting on day zero
+    fn reference_points(&self) -> Vec<(f32, f32)> {
+        let Some((_, days)) = &self.reference else {
+            return Vec::new();
+        };
+        let first_day = days
+            .iter()
+            .map(|&(day, _)| day)
+            .fold(f32::INFINITY, f32::min);
+        let (peak_day, peak_cases) = days.iter().fold((first_day, 0.0), |peak, &(day, cases)| {
+            if cases > peak.1 { (day, cases) } else { peak }
+        });
+        let scale = self.peak_infected as f32 / peak_cases.max(f32::EPSILON);
+        let stretch = match self.stats.iter().max_by_key(|stat| stat.num_infected) {
+            Some(peak) if self.reference_align_peaks && peak_day > first_day => {
+                peak.time.as_secs_f32() / (peak_day - first_day)
+            }
+            _ => 1.0,
+        };
+        days.iter()
+            .map(|&(day, cases)| ((day - first_day) * stretch * 1000.0, cases * scale))
+            .collect()
+    }
+
+    /// The settings of the two compared configs side by side, with differences highlighted
+    fn config_diff_ui(&mut self, ui: &mut Ui) {
+        ui.add_space(15.);
+        ui.horizontal(|ui| {
+            ui.heading("Scenario diff");
+            if ui.add(Button::new("Clear")).clicked() {
+                self.compared_configs.clear();
+            }
+        });
+        let (Some((name_a, a)), Some((name_b, b))) =
+            (self.compared_configs.front(), self.compared_configs.get(1))
+        else {
+            ui.add(Label::new("Drop a second config to compare against"));
+            return;
+        };
+
+        let changed = Palette::of(&self.display).warning;
+        let diff = export::config_diff(a, b);
+        ui.add(Label::new(format!(
+            "{} of {} settings differ",
+            diff.iter().filter(|field| field.differs()).count(),
+            diff.len()
+        )));
+        ScrollArea::both().show(ui, |ui| {
+            Grid::new("config_diff").striped(true).show(ui, |ui| {
+                for header in ["Setting", name_a, name_b] {
+                    ui.add(Label::new(RichText::new(header).strong()));
+                }
+                ui.end_row();
+
+                for field in &diff {
+                    let cell = |value: &str| {
+                        let text = RichText::new(value);
+                        if field.differs() {
+                            text.color(changed)
+                        } else {
+                            text
+                        }
+                    };
+                    ui.add(Label::new(cell(&field.path)));
+                    ui.add(Label::new(cell(field.a.as_deref().unwrap_or("—"))));
+                    ui.add(Label::new(cell(field.b.as_deref().unwrap_or("—"))));
+                    ui.end_row();
+                }
+            });
+        });
+    }
+
+    /// One row per simulated day, from the snapshot nearest each day boundary
+    fn daily_table_ui(&self, ui: &mut Ui) {
+        ui.add_space(15.);
+        ui.heading("Daily numbers");
+        ScrollArea::vertical().show(ui, |ui| {
+            Grid::new("daily_numbers").striped(true).show(ui, |ui| {
+                for header in [
+                    "Day",
+                    "Healthy",
+                    "Infected",
+                    "Recovered",
+                    "Dead",
+                    "Total inf.",
+                ] {
+                    ui.add(Label::new(RichText::new(header).strong()));
+                }
+                ui.end_row();
+
+                for (time, stat) in export::sample_every(&self.stats, Duration::from_secs(1)) {
+                    ui.add(Label::new(format!("{}", time.as_secs())));
+                    for count in [
+                        stat.num_healthy,
+                        stat.num_infected,
+                        stat.num_recovered,
+                        stat.num_dead,
+                        stat.cumulative_infected,
+                    ] {
+                        ui.add(Label::new(format!("{count}")));
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+    }
+
+    /// Tornado chart of the last sensitivity analysis, one bar per parameter
+    fn sensitivity_ui(&self, ui: &mut Ui) {
+        let Some(sensitivity) = &self.sensitivity else {
+            ui.add(Label::new("Run a sensitivity analysis to see this chart"));
+            return;
+        };
+        const LABEL_WIDTH: f32 = 100.0;
+
+        let palette = Palette::of(&self.display);
+        let painter = ui.painter();
+        let area = ui.available_rect_before_wrap();
+        let plot = Rect::from_min_max(
+            area.min
+                + Vec2 {
+                    x: LABEL_WIDTH,
+                    y: 0.0,
+                },
+            area.max - Vec2 { x: 10.0, y: 20.0 },
+        );
+        if !(plot.width() >= MIN_PLOT_SIZE && plot.height() >= MIN_PLOT_SIZE) {
+            return;
+        }
+
+        let (min, max) = sensitivity
+            .bars
+            .iter()
+            .flat_map(|bar| [bar.low, bar.high])
+            .fold(
+                (sensitivity.baseline, sensitivity.baseline),
+                |(min, max), value| (min.min(value), max.max(value)),
+            );
+        let span = (max - min).max(f32::EPSILON);
+        let to_x = |value: f32| plot.min.x + (value - min) / span * plot.width();
+
+        let row_height = plot.height() / sensitivity.bars.len().max(1) as f32;
+        for (i, bar) in sensitivity.bars.iter().enumerate() {
+            let top = plot.min.y + i as f32 * row_height;
+            let y_range = (top + row_height * 0.15)..=(top + row_height * 0.85);
+            for (value, color) in [
+                (bar.low, palette.mean_field),
+                (bar.high, palette.projection),
+            ] {
+                let (left, right) = (to_x(sensitivity.baseline), to_x(value));
+                painter.rect_filled(
+                    Rect::from_x_y_ranges(left.min(right)..=left.max(right), y_range.clone()),
+                    0.0,
+                    color,
+                );
+            }
+            painter.text(
+                Pos2 {
+                    x: area.min.x,
+                    y: top + row_height / 2.0,
+                },
+                Align2::LEFT_CENTER,
+                bar.param,
+                FontId::proportional(12.0),
+                palette.foreground,
+            );
+        }
+
+        // Baseline, and the scale along the bottom
+        painter.vline(
+            to_x(sensitivity.baseline),
+            plot.y_range(),
+            Stroke::new(1.0, palette.foreground),
+        );
+        for (value, align) in [
+            (min, Align2::LEFT_TOP),
+            (sensitivity.baseline, Align2::CENTER_TOP),
+            (max, Align2::RIGHT_TOP),
+        ] {
+            painter.text(
+                Pos2 {
+                    x: to_x(value),
+                    y: plot.max.y + 2.0,
+                },
+                align,
+                format!("{value:.1}"),
+                FontId::proportional(10.0),
+                palette.foreground,
+            );
+        }
+        painter.text(
+            plot.right_top(),
+            Align2::RIGHT_TOP,
+            format!(
+                "mean {}, each parameter −{p:.0}% / +{p:.0}%",
+                sensitivity.measure,
+                p = sensitivity.perturbation * 100.0
+            ),
+            FontId::proportional(11.0),
+            palette.foreground,
+        );
+    }
+
+    fn histogram_ui(&self, ui: &mut Ui, label: &str, sample: impl Fn(&RunOutcome) -> f32) {
+        let Some(ensemble) = &self.ensemble else {
+            ui.add(Label::new("Run an ensemble to see this distribution"));
+            return;
+        };
+        let samples: Vec<f32> = ensemble.runs.iter().map(sample).collect();
+        let histogram = Histogram::new(&samples, HISTOGRAM_BINS);
+        let max_count = histogram.counts.iter().copied().max().unwrap_or(0).max(1);
+
+        let palette = Palette::of(&self.display);
+        let painter = ui.painter();
+        let Some(plot) = draw_axes(
+            painter,
+            ui.available_rect_before_wrap(),
+            label,
+            "runs",
+            &[],
+            palette.foreground,
+        ) else {
+            return;
+        };
+
+        let bar_width = plot.width() / histogram.counts.len() as f32;
+        let bars = histogram.counts.iter().enumerate().map(|(i, count)| {
+            let x = plot.min.x + i as f32 * bar_width;
+            let height = *count as f32 / max_count as f32 * plot.height();
+            Shape::rect_filled(
+                Rect::from_min_max(
+                    Pos2 {
+                        x,
+                        y: plot.max.y - height,
+                    },
+                    Pos2 {
+                        x: x + bar_width - 1.0,
+                        y: plot.max.y,
+                    },
+                ),
+                0.0,
+                palette.foreground,
+            )
+        });
+        painter.extend(bars);
+
+        // Range labels underneath the first and last bars
+        for (text, x, align) in [
+            (histogram.min, plot.min.x, Align2::LEFT_TOP),
+            (histogram.max, plot.max.x, Align2::RIGHT_TOP),
+        ] {
+            painter.text(
+                Pos2 {
+                    x,
+                    y: plot.max.y + 2.0,
+                },
+                align,
+                format!("{text:.0}"),
+                FontId::proportional(10.0),
+                palette.foreground,
+            );
+        }
+    }
+
+    /// Makes the scheduled changes falling in `[from, to)`, remembering what each replaced
+    fn apply_schedule(&mut self, from: Duration, to: Duration) {
+        let days = from.as_secs_f32()..to.as_secs_f32();
+        for (day, change) in self.params.schedule.clone() {
+            if days.contains(&day) {
+                let (value, _, range) = change.param.target(&mut self.params);
+                if !self
+                    .schedule_originals
+                    .iter()
+                    .any(|&(param, _)| param == change.param)
+                {
+                    self.schedule_originals.push((change.param, *value));
+                }
+                *value = change.value.clamp(*range.start(), *range.end());
+            }
+        }
+    }
+
+    /// Puts back the values the schedule has changed
+    fn revert_schedule(&mut self) {
+        for (param, original) in self.schedule_originals.drain(..) {
+            *param.target(&mut self.params).0 = original;
+        }
+    }
+
+    /// The parameters as set before the schedule changed any, for starting other runs from
+    fn base_params(&self) -> Params {
+        let mut params = self.params.clone();
+        for &(param, original) in &self.schedule_originals {
+            *param.target(&mut params).0 = original;
+        }
+        params
+    }
+
+    /// Unpauses, restarting the frame clock so time spent paused isn't simulated in one jump
+    fn resume(&mut self) {
+        self.paused = false;
+        self.last_frame_time = Instant::now();
+    }
+
+    /// Advances the simulation by the wall-clock time since the last frame, or by
+    /// `step_speed` fixed steps in deterministic mode, as many as the speed governor allows
+    fn step(&mut self) {
+        let elapsed = self.last_frame_time.elapsed();
+        self.last_frame_time = Instant::now();
+        // Nothing can happen without anyone to happen to
+        if self.population() == 0 {
+            return;
+        }
+        self.checkpoint_if_due();
+        if self.deterministic {
+            self.take_fixed_steps();
+        } else {
+            self.advance(elapsed.mul_f32(self.step_speed));
+        }
+        self.note_milestones();
+        self.report_if_due();
+        self.film_if_due();
+    }
+
+    /// Reports the infected count once each simulated day, undercounted by
+    /// `reporting_fraction` and scattered by `reporting_noise`, catching up on every day a
+    /// step passed
+    fn report_if_due(&mut self) {
+        let first = self
+            .reported
+            .last()
+            .map_or(0, |&(last, _)| last.as_secs() + 1);
+        for day in first..=self.time_elapsed.as_secs() {
+            let day = Duration::from_secs(day);
+            // As recorded when the day began, if it was
+            let recorded = self.stats.partition_point(|stat| stat.time <= day);
+            let infected = recorded
+                .checked_sub(1)
+                .map_or(self.num_infected, |latest| self.stats[latest].num_infected);
+            let expected = infected as f32 * self.reporting_fraction;
+            let noise = 1.0 + self.reporting_noise * rng::observation_noise();
+            self.reported
+                .push((day, (expected * noise).round().max(0.0)));
+        }
+    }
+
+    /// Takes `step_speed` fixed steps, carrying fractions over, throttled by the speed governor
+    fn take_fixed_steps(&mut self) {
+        let started = Instant::now();
+        self.steps_owed += self.step_speed * self.throttle;
+        while self.steps_owed >= 1.0 {
+            self.steps_owed -= 1.0;
+            self.advance(DETERMINISTIC_DT);
+        }
+        // Back off quickly when over budget, and recover slowly once comfortably under it
+        let spent_ms = started.elapsed().as_secs_f32() * 1000.0;
+        self.throttle = if !self.speed_governor {
+            1.0
+        } else if spent_ms > self.step_budget_ms {
+            (self.throttle * THROTTLE_BACKOFF).max(MIN_THROTTLE)
+        } else if spent_ms < self.step_budget_ms * 0.8 {
+            (self.throttle * THROTTLE_RECOVERY).min(1.0)
+        } else {
+            self.throttle
+        };
+    }
+
+    /// Records milestones passed since the last frame, pinging each just once per run
+    fn note_milestones(&mut self) {
+        self.peak_infected = self.peak_infected.max(self.num_infected);
+        let min_peak = (self.population() as f32 * WAVE_MIN_PEAK).max(1.0);
+        let passed = [
+            (Milestone::FirstDeath, self.num_dead > 0),
+            (
+                Milestone::Peak,
+                self.peak_infected > self.init_infected
+                    && self.peak_infected as f32 >= min_peak
+                    && (self.num_infected as f32) <= self.peak_infected as f32 * PEAK_PASSED_RATIO,
+            ),
+            (Milestone::End, self.outbreak_over()),
+        ];
+        for (milestone, passed) in passed {
+            if passed && !self.milestones.iter().any(|&(seen, _)| seen == milestone) {
+                self.milestones.push((milestone, Instant::now()));
+            }
+        }
+    }
+
+    /// Highlight from 1 down to 0 for a counter showing `milestone`, fading after it is passed
+    fn ping(&self, milestone: Milestone) -> f32 {
+        if !self.display.milestone_pings {
+            return 0.0;
+        }
+        self.milestones
+            .iter()
+            .find(|&&(seen, _)| seen == milestone)
+            .map_or(0.0, |(_, at)| {
+                (1.0 - at.elapsed().as_secs_f32() / MILESTONE_PING_S).max(0.0)
+            })
+    }
+
+    /// Infects `init_infected` people picked at random from the susceptible, or from around
+    /// each strain's origin when both are seeded, starting the outbreak from now; under
+    /// `SimulationMode::Field`, the same share of the field instead
+    fn introduce_infection(&mut self) {
+        self.awaiting_seed = false;
+        if let Some(field) = &mut self.field {
+            field.seed(self.init_infected);
+            self.count_field();
+            return;
+        }
+        let mut candidates: Vec<(usize, Pos2)> = self
+            .grid
+            .0
+            .values()
+            .flatten()
+            .filter(|person| {
+                person.state == InfectionState::Healthy
+                    && person.vaccine.is_none()
+                    && !person.innate_immune
+            })
+            .map(|person| (person.id, person.pos))
+            .collect();
+        candidates.sort_unstable_by_key(|&(id, _)| id);
+        let boundary = self.params.boundary;
+        let mut chosen = HashSet::new();
+        // The first seeds drawn carry strain B
+        let mut strain_b = HashSet::new();
+        while chosen.len() < self.init_infected && !candidates.is_empty() {
+            let strain = if strain_b.len() < self.params.strain_b_seeds {
+                Strain::B
+            } else {
+                Strain::A
+            };
+            let index = if self.params.strain_b_seeds > 0 {
+                // Whoever is nearest a spot in the strain's cluster, as seeds are placed at the
+                // start of a run
+                let spot = strain.seed_spot(boundary);
+                (0..candidates.len())
+                    .min_by(|&a, &b| {
+                        boundary
+                            .distance(candidates[a].1, spot)
+                            .total_cmp(&boundary.distance(candidates[b].1, spot))
+                    })
+                    .expect("candidates isn't empty")
+            } else {
+                random_range(0..candidates.len())
+            };
+            let (id, _) = candidates.swap_remove(index);
+            if strain == Strain::B {
+                strain_b.insert(id);
+            }
+            chosen.insert(id);
+        }
+
+        for person in self.grid.0.values_mut().flatten() {
+            if chosen.contains(&person.id) {
+                person.state = InfectionState::Infected(0.0);
+                person.infection_duration = self.params.draw_infection_duration();
+                person.generation = 0;
+                person.ever_infected = true;
+                person.strain = if strain_b.contains(&person.id) {
+                    Strain::B
+                } else {
+                    Strain::A
+                };
+                self.line_list.infected(person.id, self.time_elapsed);
+            }
+        }
+        self.num_healthy -= chosen.len();
+        self.num_infected += chosen.len();
+        self.num_cumulative_infected += chosen.len();
+        self.origins = infected_positions(&self.grid);
+        self.front = InfectionFront::seeded(
+            &self.grid,
+            self.params.cell_size,
+            self.params.boundary,
+            self.time_elapsed,
+        );
+    }
+
+    /// Has each infectious person infect the nearest susceptible person within `spread_radius`,
+    /// under the nearest-healthy model; sources take their turns in id order, and nobody is
+    /// infected twice
+    fn spread_to_nearest(&mut self) {
+        let radius = self.params.spread_radius;
+        let cell_size = self.params.cell_size;
+        let boundary = self.params.boundary;
+        let reach = (radius / cell_size).ceil() as i32;
+        let susceptible = |person: &Person| {
+            matches!(
+                (
+                    person.state,
+                    person.quarantine,
+                    person.vaccine,
+                    person.innate_immune
+                ),
+                (InfectionState::Healthy, None, None, false)
+            )
+        };
+
+        let mut sources: Vec<&Person> = self
+            .grid
+            .0
+            .values()
+            .flatten()
+            .filter(|person| {
+                person.quarantine.is_none()
+                    && !person.hospitalized
+                    && matches!(person.state, InfectionState::Infected(_))
+            })
+            .collect();
+        sources.sort_unstable_by_key(|person| person.id);
+        // Each new case's id, with their generation and strain
+        let mut caught: BTreeMap<usize, (u32, Strain)> = BTreeMap::new();
+        for source in sources {
+            let (x, y) = cell_key(source.pos, cell_size);
+            let mut nearest: Option<(f32, usize)> = None;
+            for dx in -reach..=reach {
+                for dy in -reach..=reach {
+                    let cell = boundary.wrap_cell((x + dx, y + dy), cell_size);
+                    let Some(people) = self.grid.0.get(&cell) else {
+                        continue;
+                    };
+                    for person in people {
+                        let distance = boundary.distance(source.pos, person.pos);
+                        if distance <= radius
+                            && susceptible(person)
+                            && !caught.contains_key(&person.id)
+                            && nearest.is_none_or(|(closest, _)| distance < closest)
+                        {
+                            nearest = Some((distance, person.id));
+                        }
+                    }
+                }
+            }
+            if let Some((_, id)) = nearest {
+                caught.insert(id, (source.generation + 1, source.strain));
+            }
+        }
+
+        let mut grid = std::mem::take(&mut self.grid.0);
+        for person in grid.values_mut().flatten() {
+            if let Some(&(generation, strain)) = caught.get(&person.id) {
+                self.infect(person, generation, strain);
+            }
+        }
+        self.grid.0 = grid;
+    }
+
+    /// Infects `person`, who is healthy and out of the grid for now, with `strain` as a case of
+    /// `generation`, counting the case and marking it on the maps
+    fn infect(&mut self, person: &mut Person, generation: u32, strain: Strain) {
+        person.dose = 0.0;
+        self.num_healthy -= 1;
+        self.num_infected += 1;
+        self.num_cumulative_infected += 1;
+        if person.ever_infected {
+            self.num_reinfections += 1;
+        }
+        person.ever_infected = true;
+        self.line_list.infected(person.id, self.time_elapsed);
+        person.state = InfectionState::Infected(0.0);
+        person.infection_duration = self.params.draw_infection_duration();
+        person.generation = generation;
+        person.strain = strain;
+        self.max_generation = self.max_generation.max(generation);
+
+        let cell_size = self.params.cell_size;
+        let cell = cell_key(person.pos, cell_size);
+        let level = self.heat.entry(cell).or_default();
+        *level = (*level + HEAT_BUMP).min(1.0);
+        *self.infection_counts.entry(cell).or_default() += 1;
+        self.front.arrive(cell, cell_size, self.time_elapsed);
+    }
+
+    /// Takes a filmstrip frame if `filmstrip_interval_days` have passed since the latest one,
+    /// pausing after each frame but the first if asked to
+    fn film_if_due(&mut self) {
+        let interval = Duration::from_secs_f32(self.filmstrip_interval_days);
+        if interval.is_zero()
+            || self.filmstrip.is_full()
+            || self
+                .filmstrip
+                .frames()
+                .last()
+                .is_some_and(|latest| self.time_elapsed < latest.time + interval)
+        {
+            return;
+        }
+        let people = self
+            .grid
+            .0
+            .values()
+            .flatten()
+            .filter(|person| !matches!(person.state, InfectionState::Dying(_)))
+            .map(|person| (person.pos, person.state))
+            .collect();
+        self.filmstrip.capture(self.time_elapsed, people);
+        if self.filmstrip_pause && self.filmstrip.frames().len() > 1 {
+            self.paused = true;
+        }
+    }
+
+    /// Takes a checkpoint if `checkpoint_interval_days` have passed since the latest one
+    fn checkpoint_if_due(&mut self) {
+        let interval = Duration::from_secs_f32(self.checkpoint_interval_days);
+        if self.max_checkpoints == 0
+            || self
+                .checkpoints
+                .back()
+                .is_some_and(|latest| self.time_elapsed < latest.time() + interval)
+        {
+            return;
+        }
+        // The limit may have been lowered since the last checkpoint
+        while self.checkpoints.len() >= self.max_checkpoints {
+            self.checkpoints.pop_front();
+        }
+        self.checkpoints.push_back(Checkpoint::capture(self));
+    }
+
+    /// Restores the latest checkpoint satisfying `before`, discarding any after it, and pauses
+    fn restore_checkpoint(&mut self, before: impl Fn(Duration) -> bool) -> bool {
+        let Some(index) = self
+            .checkpoints
+            .iter()
+            .rposition(|checkpoint| before(checkpoint.time()))
+        else {
+            return false;
+        };
+        self.checkpoints.truncate(index + 1);
+        let checkpoint = self.checkpoints.pop_back().expect("index is in range");
+        checkpoint.restore(self);
+        self.checkpoints.push_back(checkpoint);
+        self.paused = true;
+        true
+    }
+
+    /// Returns to the latest checkpoint before now
+    fn step_back(&mut self) {
+        let now = self.time_elapsed;
+        self.restore_checkpoint(|time| time < now);
+    }
+
+    /// Returns to exactly `target` by restoring the latest checkpoint at or before it and
+    /// simulating forward from there
+    ///
+    /// Randomness isn't rewound, so the replay may differ from the original run.
+    fn rewind_to(&mut self, target: Duration) {
+        if !self.restore_checkpoint(|time| time <= target) {
+            return;
+        }
+        while self.time_elapsed < target {
+            self.advance(DETERMINISTIC_DT.min(target - self.time_elapsed));
+        }
+    }
+
+    /// Whether an introduced infection has since died out
+    fn outbreak_over(&self) -> bool {
+        self.num_infected == 0 && !self.awaiting_seed && self.num_cumulative_infected > 0
+    }
+
+    /// Everyone in the simulation, living or dead
+    fn population(&self) -> usize {
+        self.num_healthy + self.num_infected + self.num_recovered + self.num_dead
+    }
+
+    /// People infected at least once, however many times
+    fn num_ever_infected(&self) -> usize {
+        self.num_cumulative_infected - self.num_reinfections
+    }
+
+    /// Fraction of the living who are immune
+    ///
+    /// Both recovery and vaccination grant immunity, while it lasts, on top of anyone
+    /// innately immune.
+    fn immune_fraction(&self) -> f32 {
+        let living = self.num_healthy + self.num_infected + self.num_recovered;
+        let immune = self.num_recovered + self.num_vaccinated + self.num_innate_immune;
+        immune as f32 / living.max(1) as f32
+    }
+
+    /// Immune fraction `1 - 1/R₀` beyond which each case infects fewer than one other, if R₀ > 1
+    fn herd_immunity_threshold(&self) -> Option<f32> {
+        let r0 = ode::Rates::new(&self.params, self.population()).r0() as f32;
+        (r0 > 1.0).then(|| 1.0 - 1.0 / r0)
+    }
+
+    /// Living people currently held in quarantine
+    fn num_quarantined(&self) -> usize {
+        self.grid
+            .0
+            .values()
+            .flatten()
+            .filter(|person| {
+                person.quarantine.is_some() && !matches!(person.state, InfectionState::Dying(_))
+            })
+            .count()
+    }
+
+    /// People currently in a hospital bed
+    fn num_hospitalized(&self) -> usize {
+        self.grid
+            .0
+            .values()
+            .flatten()
+            .filter(|person| person.hospitalized)
+            .count()
+    }
+
+    /// Currently infected people carrying `strain`
+    fn num_infected_with(&self, strain: Strain) -> usize {
+        self.grid
+            .0
+            .values()
+            .flatten()
+            .filter(|person| {
+                person.strain == strain && matches!(person.state, InfectionState::Infected(_))
+            })
+            .count()
+    }
+
+    /// Sets the counts from the field's totals, rounded to whole people
+    fn count_field(&mut self) {
+        let Some(field) = &self.field else {
+            return;
+        };
+        let totals = field.totals();
+        let infected = totals.infected.round() as usize;
+        // Only the healthy can newly fall ill, so their losses are everyone ever infected
+        let newly_infected = self
+            .num_healthy
+            .saturating_sub(totals.healthy.round() as usize);
+        self.num_healthy = totals.healthy.round() as usize;
+        self.num_infected = infected;
+        self.num_recovered = totals.recovered.round() as usize;
+        self.num_dead = totals.dead.round() as usize;
+        self.num_cumulative_infected += newly_infected;
+        self.peak_infected = self.peak_infected.max(infected);
+    }
+
+    fn snapshot(&self) -> PandemicSnapshot {
+        PandemicSnapshot {
+            time: self.time_elapsed,
+            num_healthy: self.num_healthy,
+            num_infected: self.num_infected,
+            num_recovered: self.num_recovered,
+            num_dead: self.num_dead,
+            cumulative_infected: self.num_cumulative_infected,
+            reinfections: self.num_reinfections,
+        }
+    }
+
+    /// Advances the field by `dt`, in place of the agents under `SimulationMode::Field`
+    fn advance_field(&mut self, dt: Duration) {
+        if self.field.is_none() {
+            return;
+        }
+        self.time_elapsed += dt;
+        if self.awaiting_seed && self.time_elapsed.as_secs_f32() >= self.params.seed_day {
+            self.introduce_infection();
+        }
+        let Some(field) = &mut self.field else {
+            return;
+        };
+        field.advance(dt.as_secs_f64(), &self.params);
+        self.count_field();
+        self.frames_since_record += 1;
+        if self.frames_since_record >= self.record_every_n_frames {
+            self.frames_since_record = 0;
+            self.stats.push(self.snapshot());
+        }
+    }
+
+    /// Advances the simulation by `dt` of simulated time
+    fn advance(&mut self, dt: Duration) {
+        self.apply_schedule(self.time_elapsed, self.time_elapsed + dt);
+        if self.field.is_some() {
+            self.advance_field(dt);
+            return;
+        }
+        let frame_time = dt.as_secs_f32() * 1000.0;
+        let previous_day = self.time_elapsed.as_secs();
+        self.time_elapsed += dt;
+        if self.awaiting_seed && self.time_elapsed.as_secs_f32() >= self.params.seed_day {
+            self.introduce_infection();
+        }
+
+        let infection_time = self.params.infection_time_s * 1000.0;
+        let death_this_frame =
+            frame_probability(self.params.death_prob, frame_time / infection_time);
+        let infected_this_frame = |strain: Strain| {
+            frame_probability(
+                self.params.infection_prob * self.params.transmissibility(strain),
+                frame_time / 1000.0 * self.params.exposures_per_day(),
+            )
+        };
+        let infected_this_frame = [
+            infected_this_frame(Strain::A),
+            infected_this_frame(Strain::B),
+        ];
+        // Dose picked up from each infectious cell-mate this frame, and the share of a dose kept
+        // away from them, under the dose model
+        let dose_this_frame =
+            self.params.infection_prob * self.params.exposures_per_day() * frame_time / 1000.0;
+        let dose_kept = (1.0 - self.params.dose_decay).powf(frame_time / 1000.0);
+        let probabilistic = self.params.transmission_model == TransmissionModel::Probabilistic;
+        let dose_model = self.params.dose_model;
+        let environmental = self.params.environmental;
+        let deposit_this_frame = self.params.contamination_deposit * frame_time / 1000.0;
+        let detected_this_frame =
+            frame_probability(self.params.quarantine_prob, frame_time / 1000.0);
+        let vaccinated_this_frame =
+            frame_probability(self.params.vaccination_rate, frame_time / 1000.0);
+        let natural_immunity = immunity_ms(self.params.natural_immunity_days);
+        let vaccine_immunity = immunity_ms(self.params.vaccine_immunity_days);
+        let tracing = self.params.trace_coverage > 0.0;
+        let safe_zone = self.params.safe_zone.then_some(self.params.safe_zone_rect);
+        let returning_this_frame =
+            frame_probability(self.params.home_return_prob, frame_time / 1000.0);
+        // Chance of infection inside the safe zone, relative to outside it
+        let sheltered = 1.0 - self.params.safe_zone_protection as f64;
+        // Ids of cases detected this frame, whose contacts are traced once everyone has moved
+        let mut detected = Vec::new();
+        let mut beds_free = self
+            .params
+            .hospital_capacity
+            .saturating_sub(self.num_hospitalized());
+        let hospitalization_fraction = self.params.hospitalization_fraction as f64;
+
+        // Fade the infection trails
+        let heat_decay = 0.5f32.powf(dt.as_secs_f32() / HEAT_HALF_LIFE_S);
+        self.heat.retain(|_, level| {
+            *level *= heat_decay;
+            *level > 0.01
+        });
+        // Contamination wears off
+        let contamination_kept = (1.0 - self.params.contamination_decay).powf(frame_time / 1000.0);
+        self.contamination.retain(|_, levels| {
+            for level in levels.iter_mut() {
+                *level *= contamination_kept;
+            }
+            levels.iter().sum::<f32>() > 0.001
+        });
+
+        let cell_size = self.params.cell_size;
+        // Fraction of the way each heading turns towards its nearest gathering point
+        let attraction = (self.params.attraction_strength * frame_time / 1000.0).min(1.0);
+        let home_pull = self.params.home_range_strength * frame_time / 1000.0;
+        // Everyone is carried along by the drift on top of their own heading
+        let (drift_x, drift_y) = f32::sin_cos(self.params.drift_angle_deg.to_radians());
+        let drift = Vec2 {
+            x: drift_x,
+            y: drift_y,
+        } * (self.params.drift_strength * frame_time / 1000.0);
+        // Phase timings, only measured while the profiler is shown
+        let mut timings = StepTimings::default();
+        let mut mark = self.display.show_profiler.then(Instant::now);
+        let mut people_to_move = Vec::new();
+        // Where each person in `people_to_move` was before moving, in case they are blocked
+        let mut origins = Vec::new();
+        // Phase 1: move everyone and resolve their own state changes, setting aside those who
+        // leave their cell
+        for ((x_pos, y_pos), people) in self.grid.0.iter_mut() {
+            // People who have bumped into each other scatter in new random directions
+            if self.params.collisions {
+                let can_collide = |person: &Person| {
+                    person.quarantine.is_none()
+                        && !person.hospitalized
+                        && !matches!(person.state, InfectionState::Dying(_))
+                };
+                for i in 0..people.len() {
+                    for j in i + 1..people.len() {
+                        let (a, b) = (&people[i], &people[j]);
+                        if can_collide(a)
+                            && can_collide(b)
+                            && a.pos.distance(b.pos) < self.params.collision_radius
+                        {
+                            people[i].direction = random_range(0.0..(2.0 * PI));
+                            people[j].direction = random_range(0.0..(2.0 * PI));
+                        }
+                    }
+                }
+            }
+
+            // Step each individual
+            let dist_to_move = MOVE_AMOUNT * frame_time;
+            people_to_move.extend(people.extract_if(.., |person| {
+                // The recently dead stay in place until their fade-out finishes
+                if let InfectionState::Dying(t) = person.state {
+                    let new_fade_time = t + frame_time;
+                    if new_fade_time > DEATH_FADE_MS {
+                        person.state = InfectionState::Dead;
+                        origins.push(person.pos);
+                        return true;
+                    }
+                    person.state = InfectionState::Dying(new_fade_time);
+                    return false;
+                }
+
+                let from = person.pos;
+                // Quarantined people are held in place until their hold runs out
+                if let Some(left) = person.quarantine {
+                    let left = left - frame_time;
+                    person.quarantine = (left > 0.0).then_some(left);
+                } else if !person.hospitalized {
+                    // Steer towards the nearest gathering point
+                    let boundary = self.params.boundary;
+                    if let Some(target) = nearest(&self.params.attractors, person.pos, boundary) {
+                        let offset = boundary.offset(person.pos, target);
+                        let towards = f32::atan2(offset.x, offset.y);
+                        let turn = (towards - person.direction + PI).rem_euclid(2.0 * PI) - PI;
+                        person.direction += turn * attraction;
+                    }
+
+                    // Turn back towards home, harder the farther away
+                    if home_pull > 0.0 {
+                        let offset = boundary.offset(person.pos, person.home);
+                        let towards = f32::atan2(offset.x, offset.y);
+                        let turn = (towards - person.direction + PI).rem_euclid(2.0 * PI) - PI;
+                        person.direction += turn * (home_pull * offset.length()).min(1.0);
+                    }
+
+                    // Now and then people head home to the safe zone, until they reach it
+                    if let Some(zone) = safe_zone {
+                        if zone.contains(person.pos) {
+                            person.heading_home = false;
+                        } else if person.heading_home
+                            || (returning_this_frame > 0.0
+                                && random_bool(returning_this_frame * person.compliance as f64))
+                        {
+                            person.heading_home = true;
+                            let offset = boundary.offset(person.pos, zone.center());
+                            person.direction = f32::atan2(offset.x, offset.y);
+                        }
+                    }
+
+                    // Step direction
+                    let pos = &mut person.pos;
+                    let dir = person.direction;
+                    let (x_comp, y_comp) = f32::sin_cos(dir);
+                    let dist = dist_to_move * self.params.speed(person.state);
+                    pos.x += dist * x_comp + drift.x;
+                    pos.y += dist * y_comp + drift.y;
+
+                    // If OOB, bring them back inside
+                    boundary.confine(pos, &mut person.direction);
+                }
+                let pos = &person.pos;
+
+                if let InfectionState::Infected(t) = person.state {
+                    // Chance to die
+                    let died = random_bool(death_this_frame);
+                    if died {
+                        person.state = InfectionState::Dying(0.0);
+                        person.hospitalized = false;
+                        self.num_infected -= 1;
+                        self.num_dead += 1;
+                        self.line_list
+                            .ended(person.id, self.time_elapsed, Outcome::Died);
+                        return false;
+                    }
+
+                    // Detected cases are isolated for the rest of their infection, unless they
+                    // don't come forward
+                    if person.quarantine.is_none()
+                        && random_bool(detected_this_frame * person.compliance as f64)
+                    {
+                        person.quarantine = Some(person.infection_duration - t);
+                        detected.push(person.id);
+                    }
+
+                    // New cases take a free hospital bed, if they need one
+                    if t == 0.0 && beds_free > 0 && random_bool(hospitalization_fraction) {
+                        person.hospitalized = true;
+                        beds_free -= 1;
+                    }
+
+                    // Update infection time
+                    let new_infection_time = t + frame_time;
+                    person.state = if new_infection_time > person.infection_duration {
+                        self.num_infected -= 1;
+                        self.num_recovered += 1;
+                        self.line_list
+                            .ended(person.id, self.time_elapsed, Outcome::Recovered);
+                        person.immunity = Some(natural_immunity);
+                        person.hospitalized = false;
+                        InfectionState::Recovered
+                    } else {
+                        InfectionState::Infected(new_infection_time)
+                    };
+                } else if let (InfectionState::Recovered, Some(left)) =
+                    (person.state, person.immunity)
+                {
+                    // Once immunity wanes, the recovered can catch it again
+                    let left = left - frame_time;
+                    person.immunity = (left > 0.0).then_some(left);
+                    if person.immunity.is_none() {
+                        person.state = InfectionState::Healthy;
+                        self.num_recovered -= 1;
+                        self.num_healthy += 1;
+                    }
+                }
+
+                if let Some(left) = person.vaccine {
+                    let left = left - frame_time;
+                    person.vaccine = (left > 0.0).then_some(left);
+                    if person.vaccine.is_none() {
+                        self.num_vaccinated -= 1;
+                    }
+                } else if person.state == InfectionState::Healthy
+                    && !person.innate_immune
+                    && vaccinated_this_frame > 0.0
+                    && random_bool(vaccinated_this_frame * person.compliance as f64)
+                {
+                    person.vaccine = Some(vaccine_immunity);
+                    self.num_vaccinated += 1;
+                }
+
+                // Do not retain if out of grid element
+                let moved = cell_key(*pos, cell_size) != (*x_pos, *y_pos);
+                if moved {
+                    origins.push(from);
+                }
+                moved
+            }));
+        }
+        lap(&mut mark, &mut timings.movement);
+
+        if self.first_death.is_none() && self.num_dead > 0 {
+            // Those who died this frame are still in place, fading out
+            let died_at = self
+                .grid
+                .0
+                .values()
+                .flatten()
+                .find(|person| matches!(person.state, InfectionState::Dying(_)))
+                .map(|person| person.pos);
+            if let Some(pos) = died_at {
+                self.first_death = Some((pos, self.time_elapsed));
+                if self.pause_at_first_death {
+                    self.paused = true;
+                }
+            }
+        }
+
+        // Phase 2: put everyone who left their cell into their new one
+        let max_per_cell = self.params.max_per_cell;
+        for (mut person, from) in people_to_move.into_iter().zip(origins) {
+            if person.state == InfectionState::Dead {
+                continue;
+            }
+            let mut cell = cell_key(person.pos, cell_size);
+            // A full cell turns people back to where they came from; the dying take no room
+            if max_per_cell > 0
+                && self.grid.0.get(&cell).is_some_and(|people| {
+                    people
+                        .iter()
+                        .filter(|person| !matches!(person.state, InfectionState::Dying(_)))
+                        .count()
+                        >= max_per_cell
+                })
+            {
+                person.pos = from;
+                person.direction += PI;
+                cell = cell_key(from, cell_size);
+            }
+            self.grid.0.entry(cell).or_default().push(person);
+        }
+        lap(&mut mark, &mut timings.reinsertion);
+
+        // Phase 3: infections, now that every position and state is settled for the frame.
+        // Cells are independent of each other, and each cell's sources are gathered before any
+        // of its new cases, so the outcome doesn't depend on iteration order. The grid is taken
+        // out meanwhile, so new cases can be counted as they're made
+        let mut grid = std::mem::take(&mut self.grid.0);
+        for ((x_pos, y_pos), people) in grid.iter_mut() {
+            // Infection testing; quarantined people neither infect nor catch anything, and the
+            // vaccinated and innately immune don't catch anything
+            let infectious = |person: &Person| {
+                person.quarantine.is_none()
+                    && !person.hospitalized
+                    && matches!(person.state, InfectionState::Infected(_))
+            };
+            if probabilistic && (dose_model || environmental || people.iter().any(infectious)) {
+                // Each new case is pinned on one of the cell's infectious people, whose strain
+                // it catches
+                let sources: Vec<(u32, Strain)> = people
+                    .iter()
+                    .filter(|person| infectious(person))
+                    .map(|person| (person.generation, person.strain))
+                    .collect();
+                let source_dose: f32 = sources
+                    .iter()
+                    .map(|&(_, strain)| self.params.transmissibility(strain))
+                    .sum::<f32>()
+                    * dose_this_frame;
+                // The infectious contaminate the cell with their strains, which can infect anyone
+                // in it
+                let (environment_chance, levels) = if environmental {
+                    if !sources.is_empty() {
+                        let levels = self.contamination.entry((*x_pos, *y_pos)).or_default();
+                        for &(_, strain) in &sources {
+                            levels[strain as usize] += deposit_this_frame;
+                        }
+                    }
+                    let levels = self
+                        .contamination
+                        .get(&(*x_pos, *y_pos))
+                        .copied()
+                        .unwrap_or_default();
+                    let chance = frame_probability(
+                        (self.params.environmental_infection_rate * levels.iter().sum::<f32>())
+                            .min(1.0),
+                        frame_time / 1000.0,
+                    );
+                    (chance, levels)
+                } else {
+                    (0.0, [0.0; 2])
+                };
+                for person in people.iter_mut() {
+                    // Only as sheltered as they keep to the zone's precautions
+                    let shelter = if safe_zone.is_some_and(|zone| zone.contains(person.pos)) {
+                        1.0 - (1.0 - sheltered) * person.compliance as f64
+                    } else {
+                        1.0
+                    };
+                    let susceptible = matches!(
+                        (
+                            person.state,
+                            person.quarantine,
+                            person.vaccine,
+                            person.innate_immune
+                        ),
+                        (InfectionState::Healthy, None, None, false)
+                    );
+                    if !susceptible {
+                        continue;
+                    }
+                    if dose_model {
+                        if sources.is_empty() {
+                            person.dose *= dose_kept;
+                        } else {
+                            person.dose += source_dose * shelter as f32;
+                        }
+                    }
+                    let direct = if sources.is_empty() {
+                        None
+                    } else {
+                        let (source_generation, strain) = sources[random_range(0..sources.len())];
+                        let caught = if dose_model {
+                            person.dose >= self.params.dose_threshold
+                        } else {
+                            random_bool(infected_this_frame[strain as usize] * shelter)
+                        };
+                        caught.then_some((source_generation + 1, strain))
+                    };
+                    // Cases caught from the environment can't be pinned on anyone, so start a
+                    // chain of their own, of a strain drawn by its share of the contamination
+                    let caught = direct.or_else(|| {
+                        (environment_chance > 0.0 && random_bool(environment_chance * shelter))
+                            .then(|| {
+                                let share_b =
+                                    levels[Strain::B as usize] / levels.iter().sum::<f32>();
+                                let strain = if random_bool(share_b as f64) {
+                                    Strain::B
+                                } else {
+                                    Strain::A
+                                };
+                                (0, strain)
+                            })
+                    });
+                    if let Some((generation, strain)) = caught {
+                        self.infect(person, generation, strain);
+                    }
+                }
+            }
+
+            // Remember who shares this cell, for contact tracing
+            if tracing && people.len() > 1 {
+                let present: Vec<usize> = people
+                    .iter()
+                    .filter(|person| {
+                        person.quarantine.is_none()
+                            && !person.hospitalized
+                            && !matches!(person.state, InfectionState::Dying(_))
+                    })
+                    .map(|person| person.id)
+                    .collect();
+                for &id in &present {
+                    let contacts = self.contacts.entry(id).or_default();
+                    for &other in &present {
+                        if other != id {
+                            contacts.insert(other, self.time_elapsed);
+                        }
+                    }
+                }
+            }
+        }
+        self.grid.0 = grid;
+        if !probabilistic && self.time_elapsed.as_secs() > previous_day {
+            self.spread_to_nearest();
+        }
+        lap(&mut mark, &mut timings.infection);
+
+        if tracing {
+            let recent = self.time_elapsed.saturating_sub(CONTACT_MEMORY);
+            // Quarantine a share of each detected case's recent contacts, infected or not
+            if !detected.is_empty() {
+                // Sorted, so each contact's chance is drawn in a fixed order
+                let mut recent_contacts: Vec<usize> = detected
+                    .iter()
+                    .filter_map(|id| self.contacts.get(id))
+                    .flatten()
+                    .filter(|&(_, &time)| time >= recent)
+                    .map(|(&id, _)| id)
+                    .collect();
+                recent_contacts.sort_unstable();
+                let traced: HashSet<usize> = recent_contacts
+                    .into_iter()
+                    .filter(|_| random_bool(self.params.trace_coverage as f64))
+                    .collect();
+                let hold = self.params.trace_hold_days * 1000.0;
+                for person in self.grid.0.values_mut().flatten() {
+                    if traced.contains(&person.id)
+                        && !matches!(person.state, InfectionState::Dying(_))
+                        && (person.compliance >= 1.0 || random_bool(person.compliance as f64))
+                    {
+                        person.quarantine =
+                            Some(person.quarantine.map_or(hold, |left| left.max(hold)));
+                    }
+                }
+            }
+
+            // Forget contacts too old to be traced
+            self.contacts.retain(|_, contacts| {
+                contacts.retain(|_, time| *time >= recent);
+                !contacts.is_empty()
+            });
+        }
+
+        self.frames_since_record += 1;
+        if self.frames_since_record >= self.record_every_n_frames {
+            self.frames_since_record = 0;
+            self.stats.push(self.snapshot());
+            if !self.tagged.is_empty() {
+                for person in self.grid.0.values().flatten() {
+                    if self.tagged.contains(&person.id) {
+                        self.trajectories.record(
+                            person.id,
+                            self.time_elapsed,
+                            person.pos,
+                            person.state.name(),
+                        );
+                    }
+                }
+            }
+        }
+        lap(&mut mark, &mut timings.stats);
+
+        if self.herd_immunity_at.is_none()
+            && self
+                .herd_immunity_threshold()
+                .is_some_and(|threshold| self.immune_fraction() >= threshold)
+        {
+            self.herd_immunity_at = Some(self.time_elapsed);
+            if self.pause_at_herd_immunity {
+                self.paused = true;
+            }
+        }
+
+        if mark.is_some() {
+            self.profile.blend(&timings);
+        }
+    }
+}
+
+/// A background job's progress bar, with a button to cancel it; true once that's clicked
+fn job_progress(ui: &mut Ui, (finished, runs): (usize, usize)) -> bool {
+    ui.horizontal(|ui| {
+        ui.add(
+            egui::ProgressBar::new(finished as f32 / runs.max(1) as f32)
+                .desired_width(150.)
+                .text(format!("{finished}/{runs} runs")),
+        );
+        ui.add(Button::new("Cancel")).clicked()
+    })
+    .inner
+}
+
+/// A labelled slider with a numeric box alongside for typing exact values
+fn param_slider<Num: Numeric>(
+    ui: &mut Ui,
+    label: &str,
+    value: &mut Num,
+    range: RangeInclusive<Num>,
+) -> Response {
+    scaled_param_slider(ui, label, value, range, false)
+}
+
+/// `param_slider` on a logarithmic scale, for ranges spanning orders of magnitude
+fn log_param_slider<Num: Numeric>(
+    ui: &mut Ui,
+    label: &str,
+    value: &mut Num,
+    range: RangeInclusive<Num>,
+) -> Response {
+    scaled_param_slider(ui, label, value, range, true)
+}
+
+fn scaled_param_slider<Num: Numeric>(
+    ui: &mut Ui,
+    label: &str,
+    value: &mut Num,
+    range: RangeInclusive<Num>,
+    logarithmic: bool,
+) -> Response {
+    ui.add(Label::new(label));
+    ui.horizontal(|ui| {
+        let speed = (range.end().to_f64() - range.start().to_f64()) / 200.0;
+        let slider = ui.add(
+            Slider::new(value, range.clone())
+                .logarithmic(logarithmic)
+                .show_value(false),
+        );
+        let exact = ui.add(
+            DragValue::new(value)
+                .range(range)
+                .speed(speed)
+                .max_decimals(4),
+        );
+        slider | exact
+    })
+    .inner
+}
+
+/// Adds the time since `mark` to `total` and restarts `mark`, if profiling
+fn lap(mark: &mut Option<Instant>, total: &mut Duration) {
+    if let Some(start) = mark {
+        let now = Instant::now();
+        *total += now - *start;
+        *start = now;
+    }
+}
+
+/// Probability that an event with per-exposure probability `p` happens at least once over
+/// `exposures` (possibly fractional) exposures, i.e. `1 - (1 - p)^exposures`
+///
+/// Computed in `f64` via `ln_1p`/`exp_m1` so it stays accurate for the tiny exposures of
+/// slow-motion frames instead of rounding to zero.
+fn frame_probability(p: f32, exposures: f32) -> f64 {
+    let (p, exposures) = (p as f64, exposures as f64);
+    if p <= 0.0 || exposures <= 0.0 {
+        0.0
+    } else if p >= 1.0 {
+        1.0
+    } else {
+        -(exposures * (-p).ln_1p()).exp_m1()
+    }
+}
+
+/// Draws labelled axes in `color` along the left and bottom of `rect`, returning the plot area
+/// they enclose
+///
+/// `x_ticks` are labelled marks along the x axis, each at a fraction of the way across it.
+///
+/// Draws nothing and returns `None` if that area would be smaller than `MIN_PLOT_SIZE` either
+/// way, as happens when the window is squeezed.
+fn draw_axes(
+    painter: &Painter,
+    rect: Rect,
+    x_label: &str,
+    y_label: &str,
+    x_ticks: &[(f32, String)],
+    color: Color32,
+) -> Option<Rect> {
+    let min = rect.min;
+    let max = rect.max;
+
+    let x_axis_text = painter.layout_no_wrap(x_label.to_owned(), FontId::default(), color);
+    let y_axis_text = painter.layout_no_wrap(y_label.to_owned(), FontId::default(), color);
+    let tick_font = FontId::proportional(10.0);
+    // Room for a row of tick labels between the axis and its label
+    let tick_height = if x_ticks.is_empty() {
+        0.0
+    } else {
+        painter
+            .layout_no_wrap("0".to_owned(), tick_font.clone(), color)
+            .rect
+            .height()
+            + 4.0
+    };
+    let x_offset = min.x + y_axis_text.rect.height() + 5.0;
+    let y_offset = max.y - x_axis_text.rect.height() - tick_height - 5.0;
+
+    let plot = Rect::from_min_max(
+        Pos2 {
+            x: x_offset + 1.5,
+            y: min.y + 4.0,
+        },
+        Pos2 {
+            x: max.x - 4.0,
+            y: y_offset - 1.5,
+        },
+    );
+    if !(plot.width() >= MIN_PLOT_SIZE && plot.height() >= MIN_PLOT_SIZE) {
+        return None;
+    }
+
+    let _x_label = painter.add(TextShape::new(
+        Pos2 {
+            x: x_offset + (max.x - x_offset - x_axis_text.rect.width()) / 2.0,
+            y: max.y - x_axis_text.rect.height(),
+        },
+        x_axis_text,
+        color,
+    ));
+    // Rotated text extends upwards from its anchor
+    let _y_label = painter.add(
+        TextShape::new(
+            Pos2 {
+                x: min.x,
+                y: min.y + (y_offset - min.y + y_axis_text.rect.width()) / 2.0,
+            },
+            y_axis_text,
+            color,
+        )
+        .with_angle(1.5 * PI),
+    );
+
+    let _x_axis = painter.add(Shape::LineSegment {
+        points: [
+            Pos2 {
+                x: x_offset,
+                y: y_offset,
+            },
+            Pos2 {
+                x: max.x,
+                y: y_offset,
+            },
+        ],
+        stroke: Stroke::new(1.0, color),
+    });
+    let _y_axis = painter.add(Shape::LineSegment {
+        points: [
+            Pos2 {
+                x: x_offset,
+                y: min.y + 5.0,
+            },
+            Pos2 {
+                x: x_offset,
+                y: y_offset,
+            },
+        ],
+        stroke: Stroke::new(1.0, color),
+    });
+
+    for (fraction, label) in x_ticks {
+        let x = plot.min.x + fraction * plot.width();
+        painter.vline(x, y_offset..=y_offset + 3.0, Stroke::new(1.0, color));
+        painter.text(
+            Pos2 {
+                x,
+                y: y_offset + 4.0,
+            },
+            Align2::CENTER_TOP,
+            label,
+            tick_font.clone(),
+            color,
+        );
+    }
+
+    Some(plot)
+}
+
+/// The spacing in whole days, 1, 2 or 5 × 10ⁿ, that fits at most `MAX_DAY_TICKS` ticks into
+/// `span_days`
+fn day_tick_spacing(span_days: f32) -> f32 {
+    let rough = span_days / MAX_DAY_TICKS as f32;
+    let magnitude = 10f32.powf(rough.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|step| step * magnitude)
+        .find(|&spacing| spacing >= rough)
+        .unwrap_or(rough)
+        .max(1.0)
+}
+
+/// Draws the current simulated day in the top-left corner of `ui`, highlighted by `pulse` from
+/// 0 to 1
+fn draw_day_counter(ui: &Ui, day: u64, pulse: f32, palette: &Palette) {
+    let painter = ui.painter();
+    let galley = painter.layout_no_wrap(
+        format!("Day {day}"),
+        FontId::proportional(20.0),
+        palette.overlay_text,
+    );
+    let frame = Rect::from_min_size(
+        ui.max_rect().left_top() + Vec2 { x: 5.0, y: 5.0 },
+        galley.size() + Vec2 { x: 12.0, y: 6.0 },
+    );
+    let fill = palette
+        .overlay_fill
+        .lerp_to_gamma(palette.attractor, pulse * 0.6);
+    painter.rect_filled(frame, 4.0, fill);
+    painter.galley(
+        frame.min + Vec2 { x: 6.0, y: 3.0 },
+        galley,
+        palette.overlay_text,
+    );
+}
+
+/// Rings where the first death happened, at `pos` on the grid drawn in `grid_rect`, with a note
+/// of when
+fn draw_first_death(ui: &Ui, grid_rect: Rect, pos: Pos2, time: Duration, palette: &Palette) {
+    let center = grid_rect.min + pos.to_vec2() * (grid_rect.width() / X_MAX_FLOAT);
+    let painter = ui.painter();
+    painter.circle_stroke(center, 12.0, Stroke::new(2.0, palette.warning));
+    let galley = painter.layout_no_wrap(
+        format!("First death, day {:.1}", time.as_secs_f32()),
+        FontId::proportional(14.0),
+        palette.overlay_text,
+    );
+    // Beside the ring, kept inside the grid
+    let min = (center + Vec2 { x: 16.0, y: -8.0 })
+        .min(grid_rect.max - galley.size() - Vec2::splat(6.0))
+        .max(grid_rect.min);
+    let frame = Rect::from_min_size(min, galley.size() + Vec2 { x: 8.0, y: 4.0 });
+    painter.rect_filled(frame, 4.0, palette.overlay_fill);
+    painter.galley(
+        frame.min + Vec2 { x: 4.0, y: 2.0 },
+        galley,
+        palette.overlay_text,
+    );
+}
+
+/// Draws a colour key for the grid in the top-right corner of `ui`, including the second strain
+/// if it is in play, followed by a key to the subgroup shapes if they are shown
+fn draw_legend(ui: &Ui, palette: &Palette, display: &DisplayOptions, strains: bool) {
+    const ENTRIES: [(InfectionState, &str); 3] = [
+        (InfectionState::Healthy, "Healthy"),
+        (InfectionState::Infected(0.0), "Infected"),
+        (InfectionState::Recovered, "Recovered"),
+    ];
+    const ROW_HEIGHT: f32 = 16.0;
+
+    let subgroup_shapes = display.subgroup_shapes;
+    let shapes: &[Subgroup] = if subgroup_shapes { &Subgroup::ALL } else { &[] };
+    let infected_shape = if display.infected_diamonds {
+        MarkerShape::Diamond
+    } else {
+        MarkerShape::Circle
+    };
+    let rows = ENTRIES.len() + strains as usize + shapes.len();
+    let width = if subgroup_shapes || strains {
+        125.0
+    } else {
+        90.0
+    };
+
+    let painter = ui.painter();
+    let corner = ui.max_rect().right_top();
+    let frame = Rect::from_min_size(
+        Pos2 {
+            x: corner.x - width - 5.0,
+            y: corner.y + 5.0,
+        },
+        Vec2 {
+            x: width,
+            y: ROW_HEIGHT * rows as f32 + 6.0,
+        },
+    );
+    painter.rect_filled(frame, 4.0, palette.overlay_fill);
+
+    let entries = ENTRIES
+        .into_iter()
+        .map(|(state, label)| {
+            let shape = match state {
+                InfectionState::Infected(_) => infected_shape,
+                _ => MarkerShape::Circle,
+            };
+            (shape, palette.state(state), label.to_owned())
+        })
+        .chain(strains.then(|| {
+            let label = format!("Infected, {}", Strain::B);
+            (infected_shape, palette.strain_b, label)
+        }))
+        .chain(
+            shapes
+                .iter()
+                .map(|&group| (group.shape(), palette.overlay_text, format!("{group}"))),
+        );
+    for (i, (shape, color, label)) in entries.enumerate() {
+        let y = frame.min.y + 3.0 + ROW_HEIGHT * (i as f32 + 0.5);
+        let center = Pos2 {
+            x: frame.min.x + 10.0,
+            y,
+        };
+        painter.add(shape.at(center, 5.0, color, Stroke::NONE));
+        painter.text(
+            Pos2 {
+                x: frame.min.x + 22.0,
+                y,
+            },
+            Align2::LEFT_CENTER,
+            label,
+            FontId::proportional(12.0),
+            palette.overlay_text,
+        );
+    }
+}
+
+/// Draws the averaged `advance` phase timings in the bottom-left corner of `ui`
+fn draw_profiler(ui: &Ui, profile: &StepProfile, palette: &Palette) {
+    let total =
+        profile.movement_us + profile.infection_us + profile.reinsertion_us + profile.stats_us;
+    let text = format!(
+        r#"movement    {:>8.1} µs
+infection   {:>8.1} µs
+reinsertion {:>8.1} µs
+stats       {:>8.1} µs
+total       {:>8.1} µs"#,
+        profile.movement_us, profile.infection_us, profile.reinsertion_us, profile.stats_us, total
+    );
+
+    let painter = ui.painter();
+    let galley = painter.layout_no_wrap(text, FontId::monospace(11.0), palette.overlay_text);
+    let corner = ui.max_rect().left_bottom();
+    let frame = Rect::from_min_size(
+        Pos2 {
+            x: corner.x + 5.0,
+            y: corner.y - galley.size().y - 15.0,
+        },
+        galley.size() + Vec2 { x: 10.0, y: 10.0 },
+    );
+    painter.rect_filled(frame, 4.0, palette.overlay_fill);
+    painter.galley(
+        frame.min + Vec2 { x: 5.0, y: 5.0 },
+        galley,
+        palette.overlay_text,
+    );
+}
+
+/// A dropped file's name and contents, read from disk natively and given directly on the web
+fn dropped_file(file: &egui::DroppedFile) -> (String, Result<Vec<u8>, String>) {
+    let name = match &file.path {
+        Some(path) if file.name.is_empty() => path
+            .file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().into_owned()),
+        _ => file.name.clone(),
+    };
+    let contents = match (&file.bytes, &file.path) {
+        (Some(bytes), _) => Ok(bytes.to_vec()),
+        (None, Some(path)) => std::fs::read(path).map_err(|e| e.to_string()),
+        (None, None) => Err("no contents".to_owned()),
+    };
+    (name, contents)
+}
+
+/// Side length in texels of the disc texture dots are drawn with
+const DOT_TEXTURE_SIZE: usize = 64;
+
+/// Uploads a white, anti-aliased disc filling a `DOT_TEXTURE_SIZE` square but for a one-texel
+/// transparent border, for tinting into dots of any size and colour
+fn load_dot_texture(ctx: &egui::Context) -> TextureHandle {
+    let half = DOT_TEXTURE_SIZE as f32 / 2.0;
+    let radius = half - 1.0;
+    let pixels = (0..DOT_TEXTURE_SIZE * DOT_TEXTURE_SIZE)
+        .map(|i| {
+            let (x, y) = (i % DOT_TEXTURE_SIZE, i / DOT_TEXTURE_SIZE);
+            let offset = Vec2 {
+                x: x as f32 + 0.5 - half,
+                y: y as f32 + 0.5 - half,
+            };
+            // Coverage falls off over one texel at the edge
+            let alpha = (radius - offset.length() + 0.5).clamp(0.0, 1.0);
+            Color32::from_white_alpha((alpha * 255.0) as u8)
+        })
+        .collect();
+    ctx.load_texture(
+        "dot",
+        ColorImage::new([DOT_TEXTURE_SIZE; 2], pixels),
+        TextureOptions::LINEAR.with_mipmap_mode(Some(TextureFilter::Linear)),
+    )
+}
+
+/// Ordered by cell, so walking the grid, and the random draws made along the way, happen in the
+/// same order on every run and platform
+type GridMap = BTreeMap<(i32, i32), Vec<Person>>;
+/// The point in `points` closest to `pos` within `boundary`, if any
+fn nearest(points: &[Pos2], pos: Pos2, boundary: Boundary) -> Option<Pos2> {
+    points.iter().copied().min_by(|&a, &b| {
+        boundary
+            .distance(a, pos)
+            .total_cmp(&boundary.distance(b, pos))
+    })
+}
+
+/// Shortest displacement from `from` to `to` on the grid with its opposite edges joined
+fn torus_offset(from: Pos2, to: Pos2) -> Vec2 {
+    let wrap = |delta: f32, size: f32| delta - size * (delta / size).round();
+    Vec2 {
+        x: wrap(to.x - from.x, X_MAX_FLOAT),
+        y: wrap(to.y - from.y, Y_MAX_FLOAT),
+    }
+}
+
+/// Distance between `a` and `b` on the grid with its opposite edges joined
+fn torus_distance(a: Pos2, b: Pos2) -> f32 {
+    torus_offset(a, b).length()
+}
+
+/// Where everyone currently infected in `grid` is
+fn infected_positions(grid: &SpatialGrid) -> Vec<Pos2> {
+    grid.0
+        .values()
+        .flatten()
+        .filter(|person| matches!(person.state, InfectionState::Infected(_)))
+        .map(|person| person.pos)
+        .collect()
+}
+
+/// Contact cell containing `pos`, for cells `cell_size` units across
+fn cell_key(pos: Pos2, cell_size: f32) -> (i32, i32) {
+    (
+        (pos.x / cell_size).floor() as i32,
+        (pos.y / cell_size).floor() as i32,
+    )
+}
+/// Per-cell intensity of recent infection activity, in `0.0..=1.0`
+type HeatMap = HashMap<(i32, i32), f32>;
+#[derive(Clone)]
+struct SpatialGrid(GridMap);
+impl SpatialGrid {
+    /// Recovered fraction of the living in each occupied cell's 3×3 neighbourhood
+    fn neighbourhood_immunity(&self) -> Vec<((i32, i32), f32)> {
+        let counts: HashMap<(i32, i32), (usize, usize)> = self
+            .0
+            .iter()
+            .map(|(&cell, people)| {
+                let living = people
+                    .iter()
+                    .filter(|person| !matches!(person.state, InfectionState::Dying(_)))
+                    .count();
+                let recovered = people
+                    .iter()
+                    .filter(|person| person.state == InfectionState::Recovered)
+                    .count();
+                (cell, (living, recovered))
+            })
+            .collect();
+        counts
+            .keys()
+            .filter_map(|&(x, y)| {
+                let (living, recovered) = (-1..=1)
+                    .flat_map(|dx| (-1..=1).map(move |dy| (x + dx, y + dy)))
+                    .filter_map(|cell| counts.get(&cell))
+                    .fold((0, 0), |(living, recovered), &(l, r)| {
+                        (living + l, recovered + r)
+                    });
+                (living > 0).then(|| ((x, y), recovered as f32 / living as f32))
+            })
+            .collect()
+    }
+
+    fn new_with_capacity(infected: usize, total: usize, params: &Params) -> Self {
+        /// Tries at finding a well-spread spot before settling for any
+        const SPREAD_ATTEMPTS: usize = 30;
+
+        let cell_size = params.cell_size;
+        // Shrink the placement area towards the centre by the density region
+        let margin = (1.0 - params.init_density_region) / 2.0;
+        let x_margin = (X_MAX_FLOAT - 1.0) * margin;
+        let y_margin = (Y_MAX_FLOAT - 1.0) * margin;
+
+        let random_pos = || loop {
+            let pos = Pos2 {
+                x: random_range((1.0 + x_margin)..(X_MAX_FLOAT - x_margin)),
+                y: random_range((1.0 + y_margin)..(Y_MAX_FLOAT - y_margin)),
+            };
+            if params.boundary.contains(pos) {
+                break pos;
+            }
+        };
+        // Already placed positions, bucketed by `min_spacing`, for spread placement
+        let spacing = params.min_spacing;
+        let mut placed: HashMap<(i32, i32), Vec<Pos2>> = HashMap::new();
+
+        // Generate random data for new person
+        let mut rand_person = || {
+            let Pos2 { x, y } = if params.spread_placement && spacing > 0.0 {
+                // Dart throwing: reject spots too close to anyone placed, giving up after
+                // enough tries so crowded settings still place everyone
+                let mut pos = random_pos();
+                for _ in 0..SPREAD_ATTEMPTS {
+                    let (cx, cy) = cell_key(pos, spacing);
+                    let crowded = (cx - 1..=cx + 1)
+                        .flat_map(|x| (cy - 1..=cy + 1).map(move |y| (x, y)))
+                        .filter_map(|cell| placed.get(&cell))
+                        .flatten()
+                        .any(|&other| params.boundary.distance(other, pos) < spacing);
+                    if !crowded {
+                        break;
+                    }
+                    pos = random_pos();
+                }
+                placed.entry(cell_key(pos, spacing)).or_default().push(pos);
+                pos
+            } else {
+                random_pos()
+            };
+            let direction = match params.init_heading {
+                HeadingDistribution::Uniform => random_range(0.0..(2.0 * f32::consts::PI)),
+                HeadingDistribution::Aligned => params.flow_angle_deg.to_radians(),
+                HeadingDistribution::Radial => {
+                    let offset = Pos2 { x, y } - Boundary::CENTER;
+                    f32::atan2(offset.x, offset.y)
+                }
+            };
+            (x, y, direction)
+        };
+
+        // Seeds numbered from here on carry strain B
+        let first_strain_b = infected - params.strain_b_seeds.min(infected);
+        let strain_of = |id: usize| {
+            if id < first_strain_b {
+                Strain::A
+            } else {
+                Strain::B
+            }
+        };
+        let mut map = GridMap::new();
+
+        for id in 0..total {
+            let (x, y, direction) = rand_person();
+            let pos = if id < infected && params.strain_b_seeds > 0 {
+                strain_of(id).seed_spot(params.boundary)
+            } else {
+                Pos2 { x, y }
+            };
+            let (state, infection_duration) = if id < infected {
+                (
+                    InfectionState::Infected(0.0),
+                    params.draw_infection_duration(),
+                )
+            } else {
+                (InfectionState::Healthy, 0.0)
+            };
+            let innate_immune = id >= infected
+                && params.innate_immune_fraction > 0.0
+                && random_bool(params.innate_immune_fraction as f64);
+            let compliance = params.draw_compliance();
+            map.entry(cell_key(pos, cell_size))
+                .or_default()
+                .push(Person {
+                    id,
+                    pos,
+                    direction,
+                    state,
+                    infection_duration,
+                    generation: 0,
+                    quarantine: None,
+                    immunity: None,
+                    vaccine: None,
+                    innate_immune,
+                    heading_home: false,
+                    home: pos,
+                    dose: 0.0,
+                    strain: if id < infected {
+                        strain_of(id)
+                    } else {
+                        Strain::A
+                    },
+                    hospitalized: false,
+                    ever_infected: id < infected,
+                    compliance,
+                });
+        }
+
+        Self(map)
+    }
+
+    fn render(
+        &self,
+        ui: &mut Ui,
+        display: &DisplayOptions,
+        overlay: &[((i32, i32), Color32)],
+        params: &Params,
+        dot_texture: TextureId,
+        origins: Option<(&[Pos2], f32)>,
+    ) -> Rect {
+        let cell_size = params.cell_size;
+        let palette = Palette::of(display);
+        let mut avail = ui.available_size() - Vec2 { x: 10.0, y: 10.0 };
+        if params.hospital_capacity > 0 {
+            avail.x -= HOSPITAL_WIDTH + HOSPITAL_GAP;
+        }
+        let (Vec2 { x, y }, Vec2 { x: x_off, y: y_off }) = letterbox(avail);
+        let (x_ratio, y_ratio) = (x / X_MAX_FLOAT, y / Y_MAX_FLOAT);
+        let to_screen = |x: f32, y: f32| Pos2 {
+            x: x_off + 5.0 + x * x_ratio,
+            y: y_off + 5.0 + y * y_ratio,
+        };
+
+        // Shade cells underneath the people
+        ui.painter().extend(overlay.iter().map(|&((x, y), color)| {
+            let min = to_screen(x as f32 * cell_size, y as f32 * cell_size);
+            Shape::rect_filled(
+                Rect::from_min_size(
+                    min,
+                    Vec2 {
+                        x: x_ratio * cell_size,
+                        y: y_ratio * cell_size,
+                    },
+                ),
+                0.0,
+                color,
+            )
+        }));
+
+        if params.boundary == Boundary::Circle {
+            ui.painter().circle_stroke(
+                to_screen(Boundary::CENTER.x, Boundary::CENTER.y),
+                Boundary::RADIUS * x_ratio,
+                Stroke::new(1.0, palette.foreground),
+            );
+        }
+
+        // Safe zone, shaded underneath everyone
+        if params.safe_zone {
+            let zone = params.safe_zone_rect;
+            ui.painter().rect_filled(
+                Rect::from_min_max(
+                    to_screen(zone.min.x, zone.min.y),
+                    to_screen(zone.max.x, zone.max.y),
+                ),
+                0.0,
+                palette.healthy.gamma_multiply(0.15),
+            );
+        }
+
+        // Gathering points
+        for &attractor in &params.attractors {
+            ui.painter().circle_stroke(
+                to_screen(attractor.x, attractor.y),
+                8.0,
+                Stroke::new(2.0, palette.attractor),
+            );
+        }
+
+        let people = self
+            .0
+            .values()
+            .flatten()
+            .filter(|person| !person.hospitalized && display.shows(person.state));
+        // Latest generation anyone has reached, at the far end of the generation gradient
+        let max_generation = if display.generation_colors {
+            self.0
+                .values()
+                .flatten()
+                .map(|person| person.generation)
+                .max()
+                .unwrap_or(0)
+                .max(1)
+        } else {
+            1
+        };
+        let dots = people.map(|person| {
+            let (radius, fill) = match person.state {
+                // Shrink and grey out over the fade
+                InfectionState::Dying(t) => {
+                    let progress = (t / DEATH_FADE_MS).min(1.0);
+                    (
+                        5.0 * (1.0 - progress),
+                        palette
+                            .infected
+                            .lerp_to_gamma(palette.dying, progress)
+                            .gamma_multiply(1.0 - progress),
+                    )
+                }
+                InfectionState::Healthy if person.innate_immune => (5.0, palette.immune),
+                InfectionState::Infected(_) if display.generation_colors => (
+                    5.0,
+                    palette.infected.lerp_to_gamma(
+                        palette.late_generation,
+                        person.generation as f32 / max_generation as f32,
+                    ),
+                ),
+                InfectionState::Infected(_) if person.strain == Strain::B => {
+                    (5.0, palette.strain_b)
+                }
+                state => (5.0, palette.state(state)),
+            };
+            let shape = if display.infected_diamonds
+                && matches!(person.state, InfectionState::Infected(_))
+            {
+                MarkerShape::Diamond
+            } else if display.subgroup_shapes {
+                person.subgroup().shape()
+            } else {
+                MarkerShape::Circle
+            };
+            let center = to_screen(person.pos.x, person.pos.y);
+            (person, shape, center, radius, fill)
+        });
+        // Ring the quarantined
+        let ring = |person: &Person| match person.quarantine {
+            Some(_) => Stroke::new(1.5, palette.foreground),
+            None => Stroke::NONE,
+        };
+        if display.dot_mesh {
+            // One mesh of disc-textured quads, rather than tessellating thousands of circles
+            let mut mesh = Mesh::with_texture(dot_texture);
+            // Quarantine rings and non-circular markers, drawn over the mesh
+            let mut on_top = Vec::new();
+            // Scaled up so the disc inside the texture's border has the dot's radius
+            let scale = DOT_TEXTURE_SIZE as f32 / (DOT_TEXTURE_SIZE as f32 - 2.0);
+            for (person, shape, center, radius, fill) in dots {
+                if shape != MarkerShape::Circle {
+                    on_top.push(shape.at(center, radius, fill, ring(person)));
+                    continue;
+                }
+                mesh.add_rect_with_uv(
+                    Rect::from_center_size(center, Vec2::splat(2.0 * radius * scale)),
+                    Rect::from_min_max(Pos2::ZERO, Pos2 { x: 1.0, y: 1.0 }),
+                    fill,
+                );
+                if person.quarantine.is_some() {
+                    on_top.push(Shape::circle_stroke(center, radius, ring(person)));
+                }
+            }
+            ui.painter().add(Shape::mesh(mesh));
+            ui.painter().extend(on_top);
+        } else {
+            ui.painter()
+                .extend(dots.map(|(person, shape, center, radius, fill)| {
+                    shape.at(center, radius, fill, ring(person))
+                }));
+        }
+
+        // Headings, drawn on top of the dots
+        if display.show_headings {
+            const LENGTH: f32 = 12.0;
+            let living = self.0.values().flatten().filter(|person| {
+                !person.hospitalized
+                    && !matches!(person.state, InfectionState::Dying(_))
+                    && display.shows(person.state)
+            });
+            ui.painter().extend(living.map(|person| {
+                let start = to_screen(person.pos.x, person.pos.y);
+                let (x_comp, y_comp) = f32::sin_cos(person.direction);
+                Shape::LineSegment {
+                    points: [
+                        start,
+                        Pos2 {
+                            x: start.x + LENGTH * x_comp,
+                            y: start.y + LENGTH * y_comp,
+                        },
+                    ],
+                    stroke: Stroke::new(1.0, palette.foreground),
+                }
+            }));
+        }
+
+        // Where the outbreak started, with a ring pulsing outwards through each `phase`
+        if let Some((origins, phase)) = origins {
+            for &origin in origins {
+                let center = to_screen(origin.x, origin.y);
+                ui.painter()
+                    .circle_stroke(center, 7.0, Stroke::new(1.5, palette.infected));
+                ui.painter().add(CircleShape::stroke(
+                    center,
+                    7.0 + 12.0 * phase,
+                    Stroke::new(1.5, palette.infected.gamma_multiply(1.0 - phase)),
+                ));
+            }
+        }
+
+        let grid_rect = Rect::from_min_size(to_screen(0.0, 0.0), Vec2 { x, y });
+        if params.hospital_capacity > 0 {
+            self.render_hospital(ui, grid_rect, params.hospital_capacity, palette);
+        }
+        grid_rect
+    }
+
+    /// Faint edges between people sharing a cell, who could infect one another, over the grid
+    /// drawn in `grid_rect`; edges that could carry the infection are tinted
+    fn render_contacts(&self, ui: &Ui, grid_rect: Rect, palette: &Palette) {
+        let scale = grid_rect.width() / X_MAX_FLOAT;
+        let to_screen = |pos: Pos2| grid_rect.min + pos.to_vec2() * scale;
+        let mut edges = Vec::new();
+        for people in self.0.values() {
+            let present: Vec<&Person> = people
+                .iter()
+                .filter(|person| {
+                    person.quarantine.is_none()
+                        && !person.hospitalized
+                        && !matches!(person.state, InfectionState::Dying(_))
+                })
+                .collect();
+            // Every so many pairs, spread evenly over the cell's people
+            let num_pairs = present.len() * present.len().saturating_sub(1) / 2;
+            let stride = num_pairs.div_ceil(MAX_CONTACT_EDGES).max(1);
+            let pairs = present
+                .iter()
+                .enumerate()
+                .flat_map(|(i, a)| present[i + 1..].iter().map(move |b| (a, b)))
+                .step_by(stride);
+            edges.extend(pairs.map(|(a, b)| {
+                let infected =
+                    |person: &Person| matches!(person.state, InfectionState::Infected(_));
+                let susceptible = |person: &Person| {
+                    person.state == InfectionState::Healthy
+                        && person.vaccine.is_none()
+                        && !person.innate_immune
+                };
+                // Only contacts the infection could pass along are tinted
+                let color = if infected(a) && susceptible(b) || infected(b) && susceptible(a) {
+                    palette.infected.gamma_multiply(0.6)
+                } else {
+                    palette.foreground.gamma_multiply(0.3)
+                };
+                Shape::line_segment(
+                    [to_screen(a.pos), to_screen(b.pos)],
+                    Stroke::new(1.0, color),
+                )
+            }));
+        }
+        ui.painter().extend(edges);
+    }
+
+    /// The hospital beside `grid_rect`, one bed per slot, filled with its patients
+    fn render_hospital(&self, ui: &mut Ui, grid_rect: Rect, capacity: usize, palette: &Palette) {
+        let hospital = Rect::from_min_size(
+            grid_rect.right_top() + Vec2::X * HOSPITAL_GAP,
+            Vec2 {
+                x: HOSPITAL_WIDTH,
+                y: grid_rect.height(),
+            },
+        );
+        ui.painter().rect_stroke(
+            hospital,
+            0.0,
+            Stroke::new(1.0, palette.foreground),
+            StrokeKind::Inside,
+        );
+
+        // Square beds, in as many columns as keep them all inside
+        let columns = (capacity as f32 * hospital.width() / hospital.height())
+            .sqrt()
+            .ceil()
+            .max(1.0);
+        let rows = (capacity as f32 / columns).ceil();
+        let bed = (hospital.width() / columns).min(hospital.height() / rows);
+        let slot = |i: usize| {
+            let (row, column) = ((i as f32 / columns).floor(), i as f32 % columns);
+            Rect::from_min_size(
+                hospital.min + Vec2::new(column, row) * bed,
+                Vec2::splat(bed),
+            )
+            .shrink(1.0)
+        };
+        ui.painter().extend((0..capacity).map(|i| {
+            Shape::rect_stroke(
+                slot(i),
+                0.0,
+                Stroke::new(0.5, palette.foreground.gamma_multiply(0.3)),
+                StrokeKind::Inside,
+            )
+        }));
+
+        let mut patients: Vec<&Person> = self
+            .0
+            .values()
+            .flatten()
+            .filter(|person| person.hospitalized)
+            .collect();
+        patients.sort_unstable_by_key(|person| person.id);
+        ui.painter().extend(
+            patients
+                .iter()
+                .take(capacity)
+                .enumerate()
+                .map(|(i, person)| {
+                    let fill = match person.strain {
+                        Strain::B => palette.strain_b,
+                        Strain::A => palette.infected,
+                    };
+                    Shape::circle_filled(slot(i).center(), (bed / 2.0 - 1.0).min(5.0), fill)
+                }),
+        );
+    }
+}
+
+/// The largest size with the grid's aspect ratio that fits in `avail`, and its offset to centre
+/// it there
+fn letterbox(avail: Vec2) -> (Vec2, Vec2) {
+    const TARGET_RATIO: f32 = X_MAX_FLOAT / Y_MAX_FLOAT;
+    let aspect_ratio = avail.x / avail.y;
+
+    if aspect_ratio > TARGET_RATIO {
+        // x is too large
+        let target_x = avail.y * TARGET_RATIO;
+        let x_off = (avail.x - target_x) / 2.0;
+        (
+            Vec2 {
+                x: target_x,
+                y: avail.y,
+            },
+            Vec2 { x: x_off, y: 0.0 },
+        )
+    } else if aspect_ratio < TARGET_RATIO {
+        // y is too large
+        let target_y = avail.x / TARGET_RATIO;
+        let y_off = (avail.y - target_y) / 2.0;
+        (
+            Vec2 {
+                x: avail.x,
+                y: target_y,
+            },
+            Vec2 { x: 0.0, y: y_off },
+        )
+    } else {
+        (avail, Vec2::ZERO)
+    }
+}
+
+/// How people's initial headings are chosen
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+enum HeadingDistribution {
+    /// Uniformly random
+    Uniform,
+    /// Everyone moving the same way
+    Aligned,
+    /// Away from the centre of the grid
+    Radial,
+}
+impl HeadingDistribution {
+    const ALL: [Self; 3] = [Self::Uniform, Self::Aligned, Self::Radial];
+}
+impl Display for HeadingDistribution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Uniform => "Random",
+                Self::Aligned => "Aligned flow",
+                Self::Radial => "Radially outward",
+            }
+        )
+    }
+}
+
+/// Shape of the wall people bounce off
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+enum Boundary {
+    /// The edges of the grid
+    Rect,
+    /// The largest circle centred in the grid
+    Circle,
+    /// No wall: people leaving one edge of the grid reappear at the opposite one
+    Wrap,
+}
+impl Boundary {
+    const ALL: [Self; 3] = [Self::Rect, Self::Circle, Self::Wrap];
+    const CENTER: Pos2 = Pos2 {
+        x: X_MAX_FLOAT / 2.0,
+        y: Y_MAX_FLOAT / 2.0,
+    };
+    const RADIUS: f32 = if X_MAX < Y_MAX {
+        X_MAX_FLOAT / 2.0
+    } else {
+        Y_MAX_FLOAT / 2.0
+    };
+
+    fn contains(self, pos: Pos2) -> bool {
+        match self {
+            Self::Rect | Self::Wrap => {
+                (0.0..=X_MAX_FLOAT).contains(&pos.x) && (0.0..=Y_MAX_FLOAT).contains(&pos.y)
+            }
+            Self::Circle => pos.distance(Self::CENTER) <= Self::RADIUS,
+        }
+    }
+
+    /// Shortest displacement from `from` to `to`, which crosses the edges when they wrap
+    fn offset(self, from: Pos2, to: Pos2) -> Vec2 {
+        match self {
+            Self::Rect | Self::Circle => to - from,
+            Self::Wrap => torus_offset(from, to),
+        }
+    }
+
+    fn distance(self, a: Pos2, b: Pos2) -> f32 {
+        match self {
+            Self::Rect | Self::Circle => a.distance(b),
+            Self::Wrap => torus_distance(a, b),
+        }
+    }
+
+    /// The contact cell `cell` stands for, carried around to the opposite edge when wrapping
+    fn wrap_cell(self, (x, y): (i32, i32), cell_size: f32) -> (i32, i32) {
+        match self {
+            Self::Rect | Self::Circle => (x, y),
+            Self::Wrap => {
+                let columns = (X_MAX_FLOAT / cell_size).ceil() as i32;
+                let rows = (Y_MAX_FLOAT / cell_size).ceil() as i32;
+                (x.rem_euclid(columns), y.rem_euclid(rows))
+            }
+        }
+    }
+
+    /// Brings a position that has crossed the wall back inside: mirrored, turning `direction`
+    /// to match, or carried around to the opposite edge when wrapping
+    fn confine(self, pos: &mut Pos2, direction: &mut f32) {
+        match self {
+            Self::Rect => {
+                if pos.x < 0.0 {
+                    pos.x = -pos.x;
+                    *direction = -*direction;
+                } else if pos.x > X_MAX_FLOAT {
+                    pos.x = 2.0 * X_MAX_FLOAT - pos.x;
+                    *direction = -*direction;
+                }
+                if pos.y < 0.0 {
+                    pos.y = -pos.y;
+                    *direction = PI - *direction;
+                } else if pos.y > Y_MAX_FLOAT {
+                    pos.y = 2.0 * Y_MAX_FLOAT - pos.y;
+                    *direction = PI - *direction;
+                }
+            }
+            Self::Circle => {
+                let offset = *pos - Self::CENTER;
+                let distance = offset.length();
+                if distance > Self::RADIUS {
+                    // Reflect both the overshoot and the heading about the wall's normal
+                    let normal = offset / distance;
+                    *pos = Self::CENTER + normal * (2.0 * Self::RADIUS - distance).max(0.0);
+                    let (x_comp, y_comp) = f32::sin_cos(*direction);
+                    let heading = Vec2 {
+                        x: x_comp,
+                        y: y_comp,
+                    };
+                    let reflected = heading - 2.0 * heading.dot(normal) * normal;
+                    *direction = f32::atan2(reflected.x, reflected.y);
+                }
+            }
+            Self::Wrap => {
+                pos.x = pos.x.rem_euclid(X_MAX_FLOAT);
+                pos.y = pos.y.rem_euclid(Y_MAX_FLOAT);
+            }
+        }
+    }
+}
+impl Display for Boundary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Rect => "Rectangle",
+                Self::Circle => "Circle",
+                Self::Wrap => "Wrap-around",
+            }
+        )
+    }
+}
+
+/// What the grid is populated with
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+enum SimulationMode {
+    Agents,
+    Field,
+}
+impl SimulationMode {
+    const ALL: [Self; 2] = [Self::Agents, Self::Field];
+
+    fn description(self) -> &'static str {
+        match self {
+            Self::Agents => "Individual people, each moving and catching the infection by chance",
+            Self::Field => {
+                "Counts of people in each cell that infect within the cell and diffuse to \
+                 neighbouring cells, for populations far too large to simulate one by one; \
+                 smooth, with no chance involved, and at the contact density of \"Total people\""
+            }
+        }
+    }
+}
+impl Display for SimulationMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Agents => "Agents",
+                Self::Field => "Density field",
+            }
+        )
+    }
+}
+
+/// How compliance with the interventions varies from person to person
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+enum ComplianceDistribution {
+    Full,
+    Uniform,
+    AllOrNothing,
+}
+impl ComplianceDistribution {
+    const ALL: [Self; 3] = [Self::Full, Self::Uniform, Self::AllOrNothing];
+
+    fn description(self) -> &'static str {
+        match self {
+            Self::Full => "Everyone follows the interventions fully",
+            Self::Uniform => {
+                "Each person follows them partly, to a degree spread evenly around the mean"
+            }
+            Self::AllOrNothing => {
+                "The mean fraction of people follow them fully, and the rest ignore them"
+            }
+        }
+    }
+}
+impl Display for ComplianceDistribution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Full => "Full",
+                Self::Uniform => "Partial",
+                Self::AllOrNothing => "All or nothing",
+            }
+        )
+    }
+}
+
+/// How infection passes from person to person
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+enum TransmissionModel {
+    /// By chance, between people sharing a cell
+    Probabilistic,
+    /// Once a day, each infectious person infects the nearest susceptible person in reach
+    NearestHealthy,
+}
+impl TransmissionModel {
+    const ALL: [Self; 2] = [Self::Probabilistic, Self::NearestHealthy];
+
+    fn description(self) -> &'static str {
+        match self {
+            Self::Probabilistic => {
+                "People sharing a cell with an infectious person catch it by chance"
+            }
+            Self::NearestHealthy => {
+                "Once a day, each infectious person infects the nearest healthy person within the \
+                 spread radius, with no chance involved; a simple demonstration of chain \
+                 transmission"
+            }
+        }
+    }
+}
+impl Display for TransmissionModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Probabilistic => "Probabilistic",
+                Self::NearestHealthy => "Spread to nearest healthy",
+            }
+        )
+    }
+}
+
+/// What a single `Params::infection_prob` chance of infection covers
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+enum InfectionProbUnit {
+    /// One contact: the time it takes to move one grid unit in a cell with an infectious person
+    PerContact,
+    /// A whole day spent in a cell with an infectious person
+    PerDay,
+}
+impl InfectionProbUnit {
+    const ALL: [Self; 2] = [Self::PerContact, Self::PerDay];
+
+    fn description(self) -> &'static str {
+        match self {
+            Self::PerContact => {
+                "Chance of infection per contact, where a contact is the time taken to move one \
+                 grid unit (0.1 days) while sharing a cell with an infectious person"
+            }
+            Self::PerDay => {
+                "Chance of infection per day spent sharing a cell with an infectious person"
+            }
+        }
+    }
+}
+impl Display for InfectionProbUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::PerContact => "per contact",
+                Self::PerDay => "per day",
+            }
+        )
+    }
+}
+
+/// Model parameters, carried across resets and into headless runs
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct Params {
+    mode: SimulationMode,
+    /// People in the field under `SimulationMode::Field`
+    field_population: usize,
+    /// Fraction of a field cell's living that moves to each neighbouring cell per day
+    field_diffusion: f32,
+    transmission_model: TransmissionModel,
+    /// Farthest, in grid units, the nearest-healthy model reaches for someone to infect
+    spread_radius: f32,
+    /// Chance of catching the infection from a cell-mate, per `infection_prob_unit`
+    infection_prob: f32,
+    infection_prob_unit: InfectionProbUnit,
+    /// Infect once a dose accumulated from infectious cell-mates reaches `dose_threshold`,
+    /// instead of by chance each frame
+    dose_model: bool,
+    dose_threshold: f32,
+    /// Fraction of the accumulated dose cleared per day away from the infectious
+    dose_decay: f32,
+    /// Infectious people leave contamination in their cell that can infect others later
+    environmental: bool,
+    /// Contamination each infectious person adds to their cell per day
+    contamination_deposit: f32,
+    /// Fraction of a cell's contamination that wears off per day
+    contamination_decay: f32,
+    /// Chance of infection per day per unit of contamination in one's cell
+    environmental_infection_rate: f32,
+    infection_time_s: f32,
+    /// Standard deviation of individual infection times, as a fraction of `infection_time_s`
+    duration_variability: f32,
+    death_prob: f32,
+    /// Side length of a contact cell; people sharing a cell can infect each other
+    cell_size: f32,
+    /// Capacity of a contact cell, or 0 for no limit
+    max_per_cell: usize,
+    /// Scatter people in the same cell who come within `collision_radius` of each other
+    collisions: bool,
+    collision_radius: f32,
+    /// Simulated day the initial infected are introduced, or 0 to start with them
+    seed_day: f32,
+    /// Fraction of the initially healthy who can never be infected
+    innate_immune_fraction: f32,
+    /// How many of the initial infected carry strain B rather than A; when any do, each
+    /// strain's seeds start clustered around their own origin
+    strain_b_seeds: usize,
+    /// Strain B's chance of infection relative to strain A's
+    strain_b_transmissibility: f32,
+    /// Fraction of each grid dimension, centred, that people are initially placed within
+    init_density_region: f32,
+    /// Place people at least `min_spacing` apart where possible, instead of uniformly
+    spread_placement: bool,
+    min_spacing: f32,
+    boundary: Boundary,
+    init_heading: HeadingDistribution,
+    /// Heading of everyone under `HeadingDistribution::Aligned`, clockwise from straight down
+    flow_angle_deg: f32,
+    /// Gathering points that people's headings are drawn towards
+    attractors: Vec<Pos2>,
+    /// Rate per day at which headings turn towards the nearest gathering point
+    attraction_strength: f32,
+    /// Rate per day, per grid unit away from home, at which headings turn back towards home
+    home_range_strength: f32,
+    /// Movement speed multipliers for each state
+    healthy_speed: f32,
+    infected_speed: f32,
+    recovered_speed: f32,
+    /// Speed of the drift carrying everyone along
+    drift_strength: f32,
+    /// Direction of the drift, clockwise from straight down
+    drift_angle_deg: f32,
+    /// Chance per day that an infected person is detected and quarantined
+    quarantine_prob: f32,
+    /// Fraction of a detected case's recent cell-mates that are traced and quarantined
+    trace_coverage: f32,
+    /// How long traced contacts are held in quarantine
+    trace_hold_days: f32,
+    /// Chance per day that an unvaccinated healthy person is vaccinated
+    vaccination_rate: f32,
+    /// Beds in the hospital, or 0 for no hospital
+    hospital_capacity: usize,
+    /// Fraction of new cases admitted to hospital while it has free beds
+    hospitalization_fraction: f32,
+    /// A sheltered region, in grid units, that people return to and are protected in
+    safe_zone: bool,
+    safe_zone_rect: Rect,
+    /// Fraction by which the chance of infection is cut inside the safe zone
+    safe_zone_protection: f32,
+    /// Chance per day that someone outside the safe zone heads back to it
+    home_return_prob: f32,
+    /// How individual compliance with the interventions varies, around `mean_compliance`
+    compliance: ComplianceDistribution,
+    mean_compliance: f32,
+    /// Changes made as the run reaches each simulated day, in the order listed
+    schedule: Vec<(f32, ParamChange)>,
+    /// How long immunity lasts after recovery or vaccination, or 0 for life
+    natural_immunity_days: f32,
+    vaccine_immunity_days: f32,
+}
+impl Params {
+    /// Exposures per day spent sharing a cell with an infectious person, each with an
+    /// `infection_prob` chance of infection
+    fn exposures_per_day(&self) -> f32 {
+        match self.infection_prob_unit {
+            InfectionProbUnit::PerContact => MOVE_AMOUNT * 1000.0,
+            InfectionProbUnit::PerDay => 1.0,
+        }
+    }
+
+    /// Chance of infection from `strain`, relative to `infection_prob`
+    fn transmissibility(&self, strain: Strain) -> f32 {
+        match strain {
+            Strain::A => 1.0,
+            Strain::B => self.strain_b_transmissibility,
+        }
+    }
+
+    /// Movement speed multiplier for people in `state`
+    fn speed(&self, state: InfectionState) -> f32 {
+        match state {
+            InfectionState::Healthy => self.healthy_speed,
+            InfectionState::Infected(_) => self.infected_speed,
+            InfectionState::Recovered => self.recovered_speed,
+            InfectionState::Dying(_) | InfectionState::Dead => 0.0,
+        }
+    }
+
+    /// Draws an individual infection time in ms, normally distributed around `infection_time_s`
+    fn draw_infection_duration(&self) -> f32 {
+        let mean = self.infection_time_s * 1000.0;
+        if self.duration_variability <= 0.0 {
+            return mean;
+        }
+        let z = rng::random_normal();
+        // Truncated so nobody recovers implausibly fast
+        (mean * (1.0 + self.duration_variability * z)).max(mean * 0.1)
+    }
+
+    /// Draws an individual's compliance with the interventions, from 0 for none to 1 for full
+    fn draw_compliance(&self) -> f32 {
+        let mean = self.mean_compliance.clamp(0.0, 1.0);
+        match self.compliance {
+            ComplianceDistribution::Full => 1.0,
+            // As wide as fits within [0, 1] around the mean
+            ComplianceDistribution::Uniform => {
+                let half_width = mean.min(1.0 - mean);
+                random_range((mean - half_width)..=(mean + half_width))
+            }
+            ComplianceDistribution::AllOrNothing => {
+                if random_bool(mean as f64) {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+/// Immunity lasting `days` in ms, or forever if `days` is 0
+fn immunity_ms(days: f32) -> f32 {
+    if days > 0.0 {
+        days * 1000.0
+    } else {
+        f32::INFINITY
+    }
+}
+impl Default for Params {
+    fn default() -> Self {
+        Self {
+            infection_prob: 0.5,
+            mode: SimulationMode::Agents,
+            field_population: 1_000_000,
+            field_diffusion: 0.5,
+            transmission_model: TransmissionModel::Probabilistic,
+            spread_radius: 2.0,
+            infection_prob_unit: InfectionProbUnit::PerContact,
+            dose_model: false,
+            dose_threshold: 0.5,
+            dose_decay: 0.5,
+            environmental: false,
+            contamination_deposit: 1.0,
+            contamination_decay: 0.5,
+            environmental_infection_rate: 0.05,
+            infection_time_s: 14.0,
+            duration_variability: 0.0,
+            death_prob: 0.1,
+            cell_size: 1.0,
+            max_per_cell: 0,
+            collisions: false,
+            collision_radius: 0.3,
+            seed_day: 0.0,
+            innate_immune_fraction: 0.0,
+            strain_b_seeds: 0,
+            strain_b_transmissibility: 1.0,
+            init_density_region: 1.0,
+            spread_placement: false,
+            min_spacing: 1.0,
+            boundary: Boundary::Rect,
+            init_heading: HeadingDistribution::Uniform,
+            flow_angle_deg: 90.0,
+            attractors: Vec::new(),
+            attraction_strength: 1.0,
+            home_range_strength: 0.0,
+            healthy_speed: 1.0,
+            infected_speed: 1.0,
+            recovered_speed: 1.0,
+            drift_strength: 0.0,
+            drift_angle_deg: 90.0,
+            quarantine_prob: 0.0,
+            trace_coverage: 0.0,
+            trace_hold_days: 14.0,
+            vaccination_rate: 0.0,
+            hospital_capacity: 0,
+            hospitalization_fraction: 0.1,
+            safe_zone: false,
+            safe_zone_rect: Rect::from_min_size(Pos2 { x: 5.0, y: 5.0 }, Vec2 { x: 15.0, y: 10.0 }),
+            safe_zone_protection: 0.8,
+            compliance: ComplianceDistribution::Full,
+            mean_compliance: 0.7,
+            schedule: Vec::new(),
+            home_return_prob: 0.2,
+            natural_immunity_days: 0.0,
+            vaccine_immunity_days: 0.0,
+        }
+    }
+}
+
+/// A parameter the arrow keys can nudge, or a schedule can change, during a run
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+enum Nudge {
+    InfectionProb,
+    InfectionTime,
+    DeathProb,
+    CellSize,
+    InfectedSpeed,
+    DetectionProb,
+    VaccinationRate,
+}
+impl Nudge {
+    const ALL: [Self; 7] = [
+        Self::InfectionProb,
+        Self::InfectionTime,
+        Self::DeathProb,
+        Self::CellSize,
+        Self::InfectedSpeed,
+        Self::DetectionProb,
+        Self::VaccinationRate,
+    ];
+
+    /// The parameter in `params`, with its step per key press and its allowed range
+    fn target(self, params: &mut Params) -> (&mut f32, f32, RangeInclusive<f32>) {
+        match self {
+            Self::InfectionProb => (&mut params.infection_prob, 0.01, PROBABILITY_RANGE),
+            Self::InfectionTime => (&mut params.infection_time_s, 0.5, INFECTION_TIME_RANGE),
+            Self::DeathProb => (&mut params.death_prob, 0.01, PROBABILITY_RANGE),
+            Self::CellSize => (&mut params.cell_size, 0.05, CELL_SIZE_RANGE),
+            Self::InfectedSpeed => (&mut params.infected_speed, 0.05, SPEED_RANGE),
+            Self::DetectionProb => (&mut params.quarantine_prob, 0.01, PROBABILITY_RANGE),
+            Self::VaccinationRate => (&mut params.vaccination_rate, 0.01, PROBABILITY_RANGE),
+        }
+    }
+}
+impl Display for Nudge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::InfectionProb => "Infection probability",
+                Self::InfectionTime => "Infection time",
+                Self::DeathProb => "Death probability",
+                Self::CellSize => "Contact cell size",
+                Self::InfectedSpeed => "Infected speed",
+                Self::DetectionProb => "Detection probability",
+                Self::VaccinationRate => "Vaccination rate",
+            }
+        )
+    }
+}
+
+/// A parameter set to a new value at a point in a schedule
+#[derive(Clone, Copy, Serialize, Deserialize)]
+struct ParamChange {
+    param: Nudge,
+    value: f32,
+}
+impl Display for ParamChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} → {}", self.param, self.value)
+    }
+}
 
-            // Infection testing
-            let contains_infected = people
-                .iter()
-                .any(|person| matches!(person.state, InfectionState::Infected(_)));
-            if contains_infected {
-                for person in people {
-                    match (person.state, random_bool(1.0 - not_infected_this_frame)) {
-                        (InfectionState::Healthy, true) => {
-                            self.num_healthy -= 1;
-                            self.num_infected += 1;
-                            person.state = InfectionState::Infected(0.0)
-                        }
-                        _ => {}
-                    }
-                }
+/// Per-cell statistic shaded underneath the grid
+#[derive(Clone, Copy, PartialEq)]
+enum CellOverlay {
+    None,
+    /// Recent infections, fading over time
+    Heat,
+    /// Every infection that has ever happened in a cell
+    InfectionCount,
+    /// Contamination left behind under environmental transmission
+    Contamination,
+    /// Cells whose neighbourhood is immune enough to shield the susceptible people in it
+    LocalImmunity,
+}
+impl CellOverlay {
+    const ALL: [Self; 5] = [
+        Self::None,
+        Self::Heat,
+        Self::InfectionCount,
+        Self::Contamination,
+        Self::LocalImmunity,
+    ];
+}
+impl Display for CellOverlay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::None => "None",
+                Self::Heat => "Infection trails",
+                Self::InfectionCount => "Total infections",
+                Self::Contamination => "Contamination",
+                Self::LocalImmunity => "Local herd immunity",
             }
+        )
+    }
+}
+
+/// Rendering toggles, carried across resets
+#[derive(Clone)]
+struct DisplayOptions {
+    overlay: CellOverlay,
+    /// Recovered fraction of a neighbourhood's living at which the local herd immunity overlay
+    /// counts its centre cell as protected
+    local_immunity_threshold: f32,
+    /// Which states are drawn on the grid, to focus on some of them
+    show_healthy: bool,
+    show_infected: bool,
+    show_recovered: bool,
+    fade_deaths: bool,
+    show_headings: bool,
+    show_profiler: bool,
+    show_day_counter: bool,
+    show_daily_table: bool,
+    show_mean_field: bool,
+    /// Plot the daily reported infections alongside the true count
+    show_reported: bool,
+    /// Label the time axis with day numbers
+    day_ticks: bool,
+    show_projection: bool,
+    /// Show several graphs at once instead of the selected one
+    dashboard: bool,
+    /// Mark where the initial infected were when the outbreak started
+    show_origins: bool,
+    /// Briefly highlight counters as milestones like the first death are passed
+    milestone_pings: bool,
+    /// Width of plotted series, in points
+    line_thickness: f32,
+    /// Draw each subgroup with its own marker shape instead of all as circles
+    subgroup_shapes: bool,
+    /// Colours told apart with red-green colour blindness, in place of the standard ones
+    color_blind_safe: bool,
+    /// Draw the infected as diamonds, so they stand out by shape as well as colour
+    infected_diamonds: bool,
+    /// Shade the infected by how many transmissions they are from a seed case
+    generation_colors: bool,
+    /// While paused, link people sharing a cell
+    show_contacts: bool,
+    /// Draw people as one batched mesh instead of individual circle shapes
+    dot_mesh: bool,
+    target_fps: f32,
+    theme: Theme,
+    /// Stop the simulation clock while the browser tab is hidden
+    pause_when_hidden: bool,
+}
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        Self {
+            overlay: CellOverlay::None,
+            local_immunity_threshold: 0.6,
+            show_healthy: true,
+            show_infected: true,
+            show_recovered: true,
+            fade_deaths: true,
+            show_headings: false,
+            show_profiler: false,
+            show_day_counter: true,
+            show_daily_table: false,
+            show_mean_field: false,
+            show_reported: false,
+            day_ticks: true,
+            show_projection: false,
+            dashboard: false,
+            show_origins: true,
+            milestone_pings: true,
+            line_thickness: 2.0,
+            subgroup_shapes: false,
+            generation_colors: false,
+            show_contacts: false,
+            color_blind_safe: false,
+            infected_diamonds: false,
+            dot_mesh: true,
+            target_fps: 60.0,
+            theme: Theme::Dark,
+            pause_when_hidden: true,
         }
-        // Move all people that need to be moved
-        for person in people_to_move {
-            if person.state == InfectionState::Dead {
-                continue;
-            }
-            self.grid
-                .0
-                .entry((person.pos.x as i32, person.pos.y as i32))
-                .or_default()
-                .push(person);
+    }
+}
+impl DisplayOptions {
+    /// Whether people in `state` are drawn on the grid
+    fn shows(&self, state: InfectionState) -> bool {
+        match state {
+            InfectionState::Healthy => self.show_healthy,
+            InfectionState::Infected(_) => self.show_infected,
+            InfectionState::Recovered => self.show_recovered,
+            InfectionState::Dying(_) => self.fade_deaths,
+            InfectionState::Dead => false,
         }
-
-        self.stats.push(PandemicSnapshot {
-            time: self.time_elapsed,
-            num_healthy: self.num_healthy,
-            num_infected: self.num_infected,
-            num_recovered: self.num_recovered,
-            num_dead: self.num_dead,
-        });
     }
 }
 
-type GridMap = HashMap<(i32, i32), Vec<Person>>;
-struct SpatialGrid(GridMap);
-impl SpatialGrid {
-    fn new_with_capacity(infected: usize, total: usize) -> Self {
-        // Generate random data for new person
-        fn rand_person() -> (f32, f32, f32) {
-            let (x, y) = (
-                random_range(1.0..X_MAX_FLOAT),
-                random_range(1.0..Y_MAX_FLOAT),
-            );
-            let direction = random_range(0.0..(2.0 * f32::consts::PI));
-            (x, y, direction)
+#[derive(Clone, Copy)]
+struct Person {
+    id: usize,
+    pos: Pos2,
+    direction: f32,
+    state: InfectionState,
+    /// Length of this person's current or latest infection in ms, drawn when infected
+    infection_duration: f32,
+    /// Transmissions between the seed and this person's latest infection; seeds are 0
+    generation: u32,
+    /// Time left in quarantine in ms, if held there
+    quarantine: Option<f32>,
+    /// Time left in ms before immunity from the latest recovery wanes
+    immunity: Option<f32>,
+    /// Time left in ms before vaccine protection wanes, if vaccinated
+    vaccine: Option<f32>,
+    /// Immune from the start and for good, without ever having been infected
+    innate_immune: bool,
+    /// On the way back to the safe zone
+    heading_home: bool,
+    /// Where this person started, which the home range pull draws them back towards
+    home: Pos2,
+    /// Viral dose accumulated from infectious cell-mates, under the dose model
+    dose: f32,
+    /// Strain of this person's current or latest infection; strain A for anyone never infected
+    strain: Strain,
+    /// In a hospital bed, out of the grid, until their infection ends
+    hospitalized: bool,
+    /// Has been infected at some point, so any further infection is a reinfection
+    ever_infected: bool,
+    /// How strongly the interventions apply to this person, from 0 for not at all to 1
+    compliance: f32,
+}
+
+impl Person {
+    fn subgroup(&self) -> Subgroup {
+        if self.innate_immune {
+            Subgroup::InnatelyImmune
+        } else if self.vaccine.is_some() {
+            Subgroup::Vaccinated
+        } else {
+            Subgroup::Unvaccinated
         }
+    }
+}
 
-        let mut map: GridMap = HashMap::with_capacity(total);
+/// Part of the population that can be told apart on the grid by marker shape
+#[derive(Clone, Copy, PartialEq)]
+enum Subgroup {
+    Unvaccinated,
+    Vaccinated,
+    InnatelyImmune,
+}
+impl Subgroup {
+    const ALL: [Self; 3] = [Self::Unvaccinated, Self::Vaccinated, Self::InnatelyImmune];
 
-        for _ in 0..infected {
-            let (x, y, direction) = rand_person();
-            map.entry((x as i32, y as i32)).or_default().push(Person {
-                pos: Pos2 { x, y },
-                direction,
-                state: InfectionState::Infected(0.0),
-            });
+    fn shape(self) -> MarkerShape {
+        match self {
+            Self::Unvaccinated => MarkerShape::Circle,
+            Self::Vaccinated => MarkerShape::Square,
+            Self::InnatelyImmune => MarkerShape::Triangle,
         }
+    }
+}
+impl Display for Subgroup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Unvaccinated => "Unvaccinated",
+                Self::Vaccinated => "Vaccinated",
+                Self::InnatelyImmune => "Innately immune",
+            }
+        )
+    }
+}
 
-        for _ in 0..(total - infected) {
-            let (x, y, direction) = rand_person();
-            map.entry((x as i32, y as i32)).or_default().push(Person {
-                pos: Pos2 { x, y },
-                direction,
-                state: InfectionState::Healthy,
-            });
+#[derive(Clone, Copy, PartialEq)]
+enum MarkerShape {
+    Circle,
+    Square,
+    Triangle,
+    Diamond,
+}
+impl MarkerShape {
+    /// This shape centred on `center`, fitting within a circle of `radius`
+    fn at(self, center: Pos2, radius: f32, fill: Color32, stroke: Stroke) -> Shape {
+        match self {
+            Self::Circle => Shape::Circle(CircleShape {
+                center,
+                radius,
+                fill,
+                stroke,
+            }),
+            Self::Square => Shape::Rect(RectShape::new(
+                Rect::from_center_size(center, Vec2::splat(radius * f32::consts::SQRT_2)),
+                0.0,
+                fill,
+                stroke,
+                StrokeKind::Middle,
+            )),
+            Self::Triangle => {
+                // Pointing up, with its corners on the circle
+                let corner = |angle: f32| {
+                    let (sin, cos) = angle.to_radians().sin_cos();
+                    center + radius * Vec2 { x: sin, y: -cos }
+                };
+                Shape::convex_polygon(
+                    vec![corner(0.0), corner(120.0), corner(240.0)],
+                    fill,
+                    stroke,
+                )
+            }
+            Self::Diamond => {
+                let [x, y] = [Vec2::X * radius, Vec2::Y * radius];
+                Shape::convex_polygon(
+                    vec![center - y, center + x, center + y, center - x],
+                    fill,
+                    stroke,
+                )
+            }
         }
-
-        Self(map)
     }
+}
 
-    fn render(&self, ui: &mut Ui) {
-        const TARGET_RATIO: f32 = 16.0 / 10.0;
-        let avail = ui.available_size() - Vec2 { x: 10.0, y: 10.0 };
-
-        let aspect_ratio = avail.x / avail.y;
-
-        let (x, y, x_off, y_off) = if aspect_ratio > TARGET_RATIO {
-            // x is too large
-            let target_x = avail.y * 1.6;
-            let x_off = (avail.x - target_x) / 2.0;
-            (target_x, avail.y, x_off, 0.0)
-        } else if aspect_ratio > TARGET_RATIO {
-            // y is too large
-            let target_y = avail.x / 1.6;
-            let y_off = (avail.y - target_y) / 2.0;
-            (avail.x, target_y, 0.0, y_off)
-        } else {
-            (avail.x, avail.y, 0.0, 0.0)
-        };
-        let (x_ratio, y_ratio) = (x / X_MAX_FLOAT, y / Y_MAX_FLOAT);
+/// One of two competing variants of the infection
+#[derive(Clone, Copy, PartialEq)]
+enum Strain {
+    A,
+    B,
+}
+impl Strain {
+    /// Centre of the cluster this strain's seeds are placed in, when both strains are seeded
+    fn origin(self) -> Pos2 {
+        match self {
+            Self::A => Pos2 {
+                x: X_MAX_FLOAT / 4.0,
+                y: Y_MAX_FLOAT / 2.0,
+            },
+            Self::B => Pos2 {
+                x: X_MAX_FLOAT * 3.0 / 4.0,
+                y: Y_MAX_FLOAT / 2.0,
+            },
+        }
+    }
 
-        ui.painter().extend(self.0.values().flatten().map(|person| {
-            Shape::Circle(CircleShape {
-                center: Pos2 {
-                    x: x_off + 5.0 + person.pos.x * x_ratio,
-                    y: y_off + 5.0 + person.pos.y * y_ratio,
-                },
-                radius: 5.0,
-                fill: match person.state {
-                    InfectionState::Healthy => Color32::GREEN,
-                    InfectionState::Infected(_) => Color32::RED,
-                    InfectionState::Recovered => Color32::PURPLE,
-                    InfectionState::Dead => {
-                        unreachable!("Dead people should be removed before render!")
-                    }
-                },
-                stroke: Stroke::NONE,
-            })
-        }));
+    /// A random spot within `STRAIN_CLUSTER_RADIUS` of this strain's origin, inside `boundary`
+    fn seed_spot(self, boundary: Boundary) -> Pos2 {
+        loop {
+            let angle = random_range(0.0..(2.0 * PI));
+            let radius = STRAIN_CLUSTER_RADIUS * random_range(0.0f32..1.0).sqrt();
+            let (sin, cos) = angle.sin_cos();
+            let pos = self.origin() + radius * Vec2 { x: sin, y: cos };
+            if boundary.contains(pos) {
+                break pos;
+            }
+        }
     }
 }
-
-#[derive(Clone, Copy)]
-struct Person {
-    pos: Pos2,
-    direction: f32,
-    state: InfectionState,
+impl Display for Strain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::A => "strain A",
+                Self::B => "strain B",
+            }
+        )
+    }
 }
 
 #[derive(Clone, Copy, PartialEq)]
@@ -559,35 +5753,824 @@ enum InfectionState {
     Healthy,
     Infected(f32),
     Recovered,
+    /// Dead, but still fading out on the grid; carries the time since death in ms
+    Dying(f32),
     Dead,
 }
+impl InfectionState {
+    /// Lowercase name for exports
+    fn name(self) -> &'static str {
+        match self {
+            Self::Healthy => "healthy",
+            Self::Infected(_) => "infected",
+            Self::Recovered => "recovered",
+            Self::Dying(_) | Self::Dead => "dead",
+        }
+    }
+}
+/// Colours for everything painted by hand, chosen to read well against one egui theme
+struct Palette {
+    healthy: Color32,
+    infected: Color32,
+    recovered: Color32,
+    /// The innately immune, who are otherwise healthy
+    immune: Color32,
+    /// What the dead fade to from `infected`
+    dying: Color32,
+    /// Axes, graph points, the arena boundary and heading vectors
+    foreground: Color32,
+    mean_field: Color32,
+    projection: Color32,
+    /// The pinned baseline series
+    pinned: Color32,
+    /// Real case counts loaded for comparison
+    reference: Color32,
+    /// Reported infections, next to the true count
+    reported: Color32,
+    /// People infected with the second strain
+    strain_b: Color32,
+    /// What infected people shade to from `infected` as their generation rises
+    late_generation: Color32,
+    attractor: Color32,
+    warning: Color32,
+    /// Background and text of the legend and profiler boxes
+    overlay_fill: Color32,
+    overlay_text: Color32,
+}
+impl Palette {
+    const DARK: Self = Self {
+        healthy: Color32::GREEN,
+        infected: Color32::RED,
+        recovered: Color32::PURPLE,
+        immune: Color32::from_rgb(0, 200, 200),
+        dying: Color32::GRAY,
+        foreground: Color32::GRAY,
+        mean_field: Color32::LIGHT_BLUE,
+        projection: Color32::ORANGE,
+        pinned: Color32::from_gray(80),
+        reference: Color32::WHITE,
+        reported: Color32::from_rgb(255, 150, 150),
+        strain_b: Color32::from_rgb(255, 120, 0),
+        late_generation: Color32::YELLOW,
+        attractor: Color32::GOLD,
+        warning: Color32::YELLOW,
+        overlay_fill: Color32::from_black_alpha(160),
+        overlay_text: Color32::WHITE,
+    };
+    const LIGHT: Self = Self {
+        healthy: Color32::from_rgb(0, 150, 0),
+        infected: Color32::from_rgb(210, 0, 0),
+        recovered: Color32::from_rgb(120, 0, 170),
+        immune: Color32::from_rgb(0, 140, 150),
+        dying: Color32::LIGHT_GRAY,
+        foreground: Color32::DARK_GRAY,
+        mean_field: Color32::from_rgb(0, 90, 200),
+        projection: Color32::from_rgb(220, 110, 0),
+        pinned: Color32::from_gray(200),
+        reference: Color32::BLACK,
+        reported: Color32::from_rgb(200, 90, 120),
+        strain_b: Color32::from_rgb(230, 100, 0),
+        late_generation: Color32::from_rgb(200, 160, 0),
+        attractor: Color32::from_rgb(190, 140, 0),
+        warning: Color32::from_rgb(180, 100, 0),
+        overlay_fill: Color32::from_rgba_premultiplied(200, 200, 200, 200),
+        overlay_text: Color32::BLACK,
+    };
+
+    /// Okabe-Ito colours for the states, distinguishable with red-green colour blindness
+    const DARK_SAFE: Self = Self {
+        healthy: Color32::from_rgb(86, 180, 233),
+        infected: Color32::from_rgb(230, 159, 0),
+        recovered: Color32::from_rgb(204, 121, 167),
+        immune: Color32::from_rgb(0, 158, 115),
+        strain_b: Color32::from_rgb(213, 94, 0),
+        late_generation: Color32::from_rgb(240, 228, 66),
+        projection: Color32::from_rgb(240, 228, 66),
+        ..Self::DARK
+    };
+    const LIGHT_SAFE: Self = Self {
+        healthy: Color32::from_rgb(0, 114, 178),
+        infected: Color32::from_rgb(230, 159, 0),
+        recovered: Color32::from_rgb(204, 121, 167),
+        immune: Color32::from_rgb(0, 158, 115),
+        strain_b: Color32::from_rgb(213, 94, 0),
+        late_generation: Color32::from_rgb(170, 160, 0),
+        projection: Color32::from_rgb(86, 180, 233),
+        ..Self::LIGHT
+    };
+
+    fn of(display: &DisplayOptions) -> &'static Self {
+        match (display.theme, display.color_blind_safe) {
+            (Theme::Dark, false) => &Self::DARK,
+            (Theme::Light, false) => &Self::LIGHT,
+            (Theme::Dark, true) => &Self::DARK_SAFE,
+            (Theme::Light, true) => &Self::LIGHT_SAFE,
+        }
+    }
+
+    /// Colour `graph` is plotted in, matching the state it counts where there is one
+    fn series(&self, graph: GraphOptions) -> Color32 {
+        match graph {
+            GraphOptions::Healthy => self.healthy,
+            GraphOptions::Infected | GraphOptions::Cumulative => self.infected,
+            GraphOptions::Recovered | GraphOptions::Immune => self.recovered,
+            GraphOptions::Reinfections => self.reported,
+            GraphOptions::Dead
+            | GraphOptions::Rt
+            | GraphOptions::ExtinctionTime
+            | GraphOptions::DeathToll
+            | GraphOptions::Sensitivity => self.foreground,
+        }
+    }
+
+    /// Colour used for `state` on the grid and in its legend
+    fn state(&self, state: InfectionState) -> Color32 {
+        match state {
+            InfectionState::Healthy => self.healthy,
+            InfectionState::Infected(_) => self.infected,
+            InfectionState::Recovered => self.recovered,
+            InfectionState::Dying(_) => self.dying,
+            InfectionState::Dead => unreachable!("Dead people should be removed before render!"),
+        }
+    }
+}
+
+/// Axis ranges of a time series graph
+struct PlotExtent {
+    /// End of the live series, and of the time axis, in ms
+    max_time: u128,
+    time_span: u128,
+    /// Top of the value axis
+    y_max: f32,
+    /// Day ticks, as fractions along the time axis
+    ticks: Vec<(f32, String)>,
+}
 
+#[derive(Clone, PartialEq)]
 struct PandemicSnapshot {
     time: Duration,
     num_healthy: usize,
     num_infected: usize,
     num_recovered: usize,
     num_dead: usize,
+    cumulative_infected: usize,
+    /// Infections so far of people infected before
+    reinfections: usize,
+}
+
+/// Time spent in each phase of `Pandemic::advance`
+#[derive(Default)]
+struct StepTimings {
+    movement: Duration,
+    infection: Duration,
+    reinsertion: Duration,
+    stats: Duration,
+}
+
+/// Moving averages of `StepTimings`, in microseconds
+#[derive(Default)]
+struct StepProfile {
+    movement_us: f32,
+    infection_us: f32,
+    reinsertion_us: f32,
+    stats_us: f32,
+}
+impl StepProfile {
+    /// Weight of the newest frame in the moving averages
+    const SMOOTHING: f32 = 0.05;
+
+    fn blend(&mut self, timings: &StepTimings) {
+        for (average, timing) in [
+            (&mut self.movement_us, timings.movement),
+            (&mut self.infection_us, timings.infection),
+            (&mut self.reinsertion_us, timings.reinsertion),
+            (&mut self.stats_us, timings.stats),
+        ] {
+            let micros = timing.as_secs_f32() * 1e6;
+            *average += (micros - *average) * Self::SMOOTHING;
+        }
+    }
+}
+
+/// When infection first reached each cell, and how far from the seed it has spread
+#[derive(Clone)]
+struct InfectionFront {
+    /// Centroid of the initially infected
+    origin: Option<Pos2>,
+    /// Distances from `origin` are measured within this, across the edges when they wrap
+    boundary: Boundary,
+    arrivals: HashMap<(i32, i32), Duration>,
+    /// Each time the farthest infected cell from `origin` got farther, and its new distance
+    reach: Vec<(Duration, f32)>,
+}
+impl InfectionFront {
+    /// Time over which the front's speed is measured
+    const SPEED_WINDOW: Duration = Duration::from_secs(2);
+
+    /// A front starting from everyone infected in `grid`, at time `now`
+    fn seeded(grid: &SpatialGrid, cell_size: f32, boundary: Boundary, now: Duration) -> Self {
+        let seeds = infected_positions(grid);
+        let origin = (!seeds.is_empty()).then(|| {
+            if boundary == Boundary::Wrap {
+                // Each coordinate is averaged as an angle around its wrapped axis, so seeds
+                // straddling an edge centre on that edge rather than the middle of the grid
+                let circular_mean = |coord: fn(&Pos2) -> f32, size: f32| {
+                    let (sin, cos) = seeds.iter().fold((0.0, 0.0), |(sin, cos), pos| {
+                        let (s, c) = f32::sin_cos(coord(pos) / size * 2.0 * PI);
+                        (sin + s, cos + c)
+                    });
+                    f32::atan2(sin, cos).rem_euclid(2.0 * PI) / (2.0 * PI) * size
+                };
+                Pos2 {
+                    x: circular_mean(|pos| pos.x, X_MAX_FLOAT),
+                    y: circular_mean(|pos| pos.y, Y_MAX_FLOAT),
+                }
+            } else {
+                let sum = seeds
+                    .iter()
+                    .fold(Vec2::ZERO, |sum, pos| sum + pos.to_vec2());
+                (sum / seeds.len() as f32).to_pos2()
+            }
+        });
+
+        let mut front = Self {
+            origin,
+            boundary,
+            arrivals: HashMap::new(),
+            reach: Vec::new(),
+        };
+        for pos in seeds {
+            front.arrive(cell_key(pos, cell_size), cell_size, now);
+        }
+        front
+    }
+
+    /// Records an infection in `cell`, if it is the cell's first
+    fn arrive(&mut self, cell: (i32, i32), cell_size: f32, time: Duration) {
+        let Some(origin) = self.origin else {
+            return;
+        };
+        if self.arrivals.contains_key(&cell) {
+            return;
+        }
+        self.arrivals.insert(cell, time);
+
+        let center = Pos2 {
+            x: (cell.0 as f32 + 0.5) * cell_size,
+            y: (cell.1 as f32 + 0.5) * cell_size,
+        };
+        let distance = self.boundary.distance(center, origin);
+        if self
+            .reach
+            .last()
+            .is_none_or(|&(_, farthest)| distance > farthest)
+        {
+            self.reach.push((time, distance));
+        }
+    }
+
+    /// Units per day the farthest reach grew by over the trailing `SPEED_WINDOW`
+    fn speed(&self, now: Duration) -> Option<f32> {
+        let &(_, farthest) = self.reach.last()?;
+        let since = now.checked_sub(Self::SPEED_WINDOW)?;
+        let before = self.reach[..self.reach.partition_point(|&(time, _)| time <= since)]
+            .last()
+            .map_or(0.0, |&(_, distance)| distance);
+        Some((farthest - before) / Self::SPEED_WINDOW.as_secs_f32())
+    }
+}
+
+/// One peak in the number of infected
+struct Wave {
+    time: Duration,
+    peak: usize,
+    /// Infections haven't yet fallen far enough from the peak to call it
+    ongoing: bool,
+}
+
+/// A moment in a run worth drawing attention to
+#[derive(Clone, Copy, PartialEq)]
+enum Milestone {
+    FirstDeath,
+    /// Infections have turned down from their highest point
+    Peak,
+    /// Nobody is left infected
+    End,
+}
+
+/// Per-day flows between compartments
+struct TransitionRates {
+    infections: f32,
+    recoveries: f32,
+    deaths: f32,
 }
 
-#[derive(PartialEq)]
+#[derive(Clone, Copy, PartialEq)]
 enum GraphOptions {
     Healthy,
     Infected,
     Recovered,
     Dead,
+    Cumulative,
+    /// Infections of people infected before, so far
+    Reinfections,
+    /// Fraction of the living who are immune
+    Immune,
+    /// Effective reproduction number
+    Rt,
+    ExtinctionTime,
+    DeathToll,
+    Sensitivity,
+}
+impl GraphOptions {
+    const ALL: [Self; 11] = [
+        Self::Healthy,
+        Self::Infected,
+        Self::Recovered,
+        Self::Dead,
+        Self::Cumulative,
+        Self::Reinfections,
+        Self::Immune,
+        Self::Rt,
+        Self::ExtinctionTime,
+        Self::DeathToll,
+        Self::Sensitivity,
+    ];
+    /// The graphs shown together in the dashboard layout
+    const DASHBOARD: [Self; 4] = [Self::Infected, Self::Dead, Self::Cumulative, Self::Rt];
+
+    /// This graph's value in a well-mixed model state, if it has one
+    fn model_value(self, compartments: &ode::Compartments, population: usize) -> Option<f64> {
+        match self {
+            Self::Healthy => Some(compartments.healthy),
+            Self::Infected => Some(compartments.infected),
+            Self::Recovered => Some(compartments.recovered),
+            Self::Dead => Some(compartments.dead),
+            Self::Cumulative => Some(population as f64 - compartments.healthy),
+            Self::Immune => {
+                let living = compartments.healthy + compartments.infected + compartments.recovered;
+                Some(compartments.recovered / living.max(f64::EPSILON))
+            }
+            Self::Reinfections
+            | Self::Rt
+            | Self::ExtinctionTime
+            | Self::DeathToll
+            | Self::Sensitivity => None,
+        }
+    }
 }
 impl Display for GraphOptions {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "{} Individuals",
+            "{}",
             match self {
-                Self::Healthy => "Healthy",
-                Self::Infected => "Infected",
-                Self::Recovered => "Recovered",
-                Self::Dead => "Dead",
+                Self::Healthy => "Healthy Individuals",
+                Self::Infected => "Infected Individuals",
+                Self::Recovered => "Recovered Individuals",
+                Self::Dead => "Dead Individuals",
+                Self::Cumulative => "Cumulative Infections",
+                Self::Reinfections => "Cumulative Reinfections",
+                Self::Immune => "Immune Fraction",
+                Self::Rt => "Reproduction Number (Rₜ)",
+                Self::ExtinctionTime => "Time to Extinction (ensemble)",
+                Self::DeathToll => "Death Toll (ensemble)",
+                Self::Sensitivity => "Parameter Sensitivity (ensemble)",
             }
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: Duration = Duration::from_millis(16);
+
+    #[test]
+    fn schedule_leaves_base_params_alone() {
+        let mut params = Params {
+            infection_prob: 0.2,
+            ..Params::default()
+        };
+        params.schedule = vec![(
+            1.0,
+            ParamChange {
+                param: Nudge::InfectionProb,
+                value: 0.9,
+            },
+        )];
+        let mut sim = Pandemic::with_params(5, 200, params);
+        sim.checkpoint_if_due();
+        while sim.time_elapsed < Duration::from_secs(2) {
+            sim.advance(DETERMINISTIC_DT);
+        }
+        assert_eq!(sim.params.infection_prob, 0.9);
+        assert_eq!(sim.config().params.infection_prob, 0.2);
+
+        sim.rewind_to(Duration::from_millis(500));
+        assert_eq!(sim.params.infection_prob, 0.2);
+        while sim.time_elapsed < Duration::from_secs(2) {
+            sim.advance(DETERMINISTIC_DT);
+        }
+        assert_eq!(sim.params.infection_prob, 0.9);
+
+        sim.reset();
+        assert_eq!(sim.params.infection_prob, 0.2);
+        assert!(sim.schedule_originals.is_empty());
+    }
+
+    #[test]
+    fn reference_csv_parsing() {
+        let csv =
+            "# Cases by date\ndate,cases\n2020-03-01,4\n2020-03-02, 7\n\n# gap\n2020-03-03,12\n";
+        assert_eq!(
+            export::parse_reference_csv(csv),
+            Ok(vec![(0.0, 4.0), (1.0, 7.0), (2.0, 12.0)])
+        );
+        assert_eq!(
+            export::parse_reference_csv("day,cases\n3,1\n5,2"),
+            Ok(vec![(3.0, 1.0), (5.0, 2.0)])
+        );
+        assert_eq!(
+            export::parse_reference_csv("1\n2\n"),
+            Ok(vec![(0.0, 1.0), (1.0, 2.0)])
+        );
+        assert!(export::parse_reference_csv("day,cases\n1,-3").is_err());
+        assert!(export::parse_reference_csv("1,2\nx,y").is_err());
+        assert!(export::parse_reference_csv("").is_err());
+        assert!(export::parse_reference_csv("# only a comment\ncases\n").is_err());
+    }
+
+    #[test]
+    fn png_checksums_and_encoding() {
+        assert_eq!(export::crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(export::adler32(b"Wikipedia"), 0x11e6_0398);
+
+        let expected = [
+            &b"\x89PNG\r\n\x1a\n"[..],
+            // 1×1, 8-bit grayscale
+            &[0, 0, 0, 13],
+            b"IHDR",
+            &[0, 0, 0, 1, 0, 0, 0, 1, 8, 0, 0, 0, 0],
+            &[0x3a, 0x7e, 0x9b, 0x55],
+            // One stored block of the filter byte and the pixel, then their Adler-32
+            &[0, 0, 0, 13],
+            b"IDAT",
+            &[0x78, 0x01, 0x01, 0x02, 0x00, 0xfd, 0xff, 0x00, 0x80],
+            &[0x00, 0x82, 0x00, 0x81],
+            &[0xc3, 0x6e, 0x25, 0xe0],
+            &[0, 0, 0, 0],
+            b"IEND",
+            &[0xae, 0x42, 0x60, 0x82],
+        ]
+        .concat();
+        assert_eq!(
+            export::encode_png(1, 1, export::GRAYSCALE, &[0x80]),
+            expected
+        );
+    }
+
+    #[test]
+    fn pinned_series_come_from_the_pinned_run() {
+        let mut sim = Pandemic::new(5, 200);
+        while sim.time_elapsed < Duration::from_secs(3) {
+            sim.advance(DETERMINISTIC_DT);
+        }
+        // Long enough for Rₜ's trailing window, but shorter than the live run
+        let pinned = &sim.stats[..sim.stats.len() / 2];
+        let end = pinned.last().expect("a day and a half of stats").time;
+        assert!(end > RATE_WINDOW);
+        for graph in GraphOptions::ALL {
+            let series = sim.series(pinned, graph);
+            assert!(series.iter().all(|&(time, _)| time <= end));
+        }
+        assert!(!sim.series(pinned, GraphOptions::Rt).is_empty());
+        assert_eq!(sim.series(pinned, GraphOptions::Immune).len(), pinned.len());
+    }
+
+    #[test]
+    fn side_runs_leave_the_live_sequence_alone() {
+        let params = Params::default();
+        rng::reseed(Some(7));
+        let expected: u64 = random_range(0..u64::MAX);
+        rng::reseed(Some(7));
+        rng::aside(|| RunOutcome::simulate(5, 100, &params));
+        assert_eq!(random_range(0..u64::MAX), expected);
+    }
+
+    #[test]
+    fn seeded_generator_is_restored_after_a_panic() {
+        rng::reseed(Some(3));
+        let expected: u64 = random_range(0..u64::MAX);
+        rng::reseed(Some(3));
+        let panicked = std::panic::catch_unwind(|| rng::with_seed(9, || panic!("side run failed")));
+        assert!(panicked.is_err());
+        assert_eq!(random_range(0..u64::MAX), expected);
+    }
+
+    #[test]
+    fn only_seeds_start_with_strain_b() {
+        let params = Params {
+            strain_b_seeds: 3,
+            ..Params::default()
+        };
+        let sim = Pandemic::with_params(5, 200, params);
+        let strain_b: Vec<&Person> = sim
+            .grid
+            .0
+            .values()
+            .flatten()
+            .filter(|person| person.strain == Strain::B)
+            .collect();
+        assert_eq!(strain_b.len(), 3);
+        assert!(
+            strain_b
+                .iter()
+                .all(|person| matches!(person.state, InfectionState::Infected(_)))
+        );
+    }
+
+    #[test]
+    fn waves_wait_for_a_trough() {
+        let mut sim = Pandemic::new(0, 1000);
+        let mut record = |series: &[usize]| {
+            sim.stats = series
+                .iter()
+                .enumerate()
+                .map(|(day, &num_infected)| PandemicSnapshot {
+                    time: Duration::from_secs(day as u64),
+                    num_infected,
+                    ..sim.snapshot()
+                })
+                .collect::<Vec<_>>();
+            sim.waves().len()
+        };
+
+        let single: Vec<usize> = (0..=200)
+            .step_by(5)
+            .chain((0..200).rev().step_by(5))
+            .collect();
+        assert_eq!(record(&single), 1);
+        let humps: Vec<usize> = (0..=200)
+            .step_by(5)
+            .chain((40..200).rev().step_by(5))
+            .chain((40..=150).step_by(5))
+            .chain((0..150).rev().step_by(5))
+            .collect();
+        assert_eq!(record(&humps), 2);
+    }
+
+    #[test]
+    fn no_initial_infected_stays_healthy() {
+        let mut sim = Pandemic::new(0, 500);
+        for _ in 0..2000 {
+            sim.advance(DT);
+        }
+
+        assert_eq!(sim.num_healthy, 500);
+        assert_eq!(sim.num_infected + sim.num_recovered + sim.num_dead, 0);
+        assert_eq!(sim.grid.0.values().map(Vec::len).sum::<usize>(), 500);
+        assert!(
+            sim.stats
+                .iter()
+                .all(|stat| stat.num_healthy == 500 && stat.num_infected == 0)
+        );
+        assert!(
+            sim.transition_rates()
+                .is_some_and(|rates| rates.infections == 0.0)
+        );
+    }
+
+    #[test]
+    fn empty_population_is_inert() {
+        let mut sim = Pandemic::new(0, 0);
+        assert_eq!(sim.population(), 0);
+        assert!(sim.grid.0.is_empty());
+
+        sim.step();
+        assert!(sim.stats.is_empty());
+
+        for _ in 0..100 {
+            sim.advance(DT);
+        }
+        assert_eq!(sim.population(), 0);
+        assert!(sim.stats.iter().all(|stat| stat.num_healthy == 0));
+    }
+
+    #[test]
+    fn tiny_graph_area_is_skipped() {
+        let mut sim = Pandemic::new(5, 100);
+        for _ in 0..10 {
+            sim.advance(DT);
+        }
+
+        let ctx = egui::Context::default();
+        let _ = ctx.run(egui::RawInput::default(), |ctx| {
+            let painter = ctx.layer_painter(egui::LayerId::background());
+            let tiny = Rect::from_min_size(Pos2::ZERO, Vec2::splat(10.0));
+            let inverted = Rect::from_min_max(Pos2 { x: 50.0, y: 50.0 }, Pos2::ZERO);
+            let roomy = Rect::from_min_size(Pos2::ZERO, Vec2::splat(300.0));
+
+            for rect in [tiny, inverted] {
+                assert!(draw_axes(&painter, rect, "x", "y", &[], Color32::GRAY).is_none());
+                for graph in GraphOptions::DASHBOARD {
+                    sim.plot_series(&painter, rect, graph);
+                }
+            }
+            let plot = draw_axes(&painter, roomy, "x", "y", &[], Color32::GRAY).unwrap();
+            assert!(roomy.contains_rect(plot) && plot.width() > 0.0 && plot.height() > 0.0);
+        });
+    }
+
+    #[test]
+    fn graphs_handle_zero_and_one_points() {
+        fn finite(shape: &Shape) -> bool {
+            let finite_pos = |pos: &Pos2| pos.x.is_finite() && pos.y.is_finite();
+            match shape {
+                Shape::Vec(shapes) => shapes.iter().all(finite),
+                Shape::Circle(circle) => finite_pos(&circle.center) && circle.radius.is_finite(),
+                Shape::LineSegment { points, .. } => points.iter().all(finite_pos),
+                Shape::Path(path) => path.points.iter().all(finite_pos),
+                Shape::Rect(rect) => finite_pos(&rect.rect.min) && finite_pos(&rect.rect.max),
+                Shape::Text(text) => finite_pos(&text.pos),
+                _ => true,
+            }
+        }
+
+        let mut sim = Pandemic::new(5, 100);
+        sim.display.show_mean_field = true;
+        sim.display.show_projection = true;
+        let ctx = egui::Context::default();
+        for points in 0..=1 {
+            if points == 1 {
+                // A single snapshot, taken before any time has passed
+                sim.advance(Duration::ZERO);
+            }
+            assert_eq!(sim.stats.len(), points);
+
+            let output = ctx.run(egui::RawInput::default(), |ctx| {
+                let painter = ctx.layer_painter(egui::LayerId::background());
+                let rect = Rect::from_min_size(Pos2::ZERO, Vec2::splat(300.0));
+                for graph in GraphOptions::DASHBOARD {
+                    sim.plot_series(&painter, rect, graph);
+                }
+            });
+            // At least the axes are drawn, and nothing at a NaN position
+            assert!(!output.shapes.is_empty());
+            assert!(output.shapes.iter().all(|clipped| finite(&clipped.shape)));
+        }
+    }
+
+    #[test]
+    fn letterbox_matches_grid_ratio() {
+        let grid_ratio = X_MAX_FLOAT / Y_MAX_FLOAT;
+        for avail in [
+            Vec2 {
+                x: 1000.0,
+                y: 300.0,
+            },
+            Vec2 {
+                x: 300.0,
+                y: 1000.0,
+            },
+            Vec2 {
+                x: 500.0,
+                y: 500.0 / grid_ratio,
+            },
+        ] {
+            let (size, offset) = letterbox(avail);
+            assert!((size.x / size.y - grid_ratio).abs() < 1e-4);
+            // Fills one dimension and is centred in the other
+            assert!(size.x <= avail.x + 1e-3 && size.y <= avail.y + 1e-3);
+            assert!((size.x - avail.x).abs() < 1e-3 || (size.y - avail.y).abs() < 1e-3);
+            assert!((2.0 * offset.x + size.x - avail.x).abs() < 1e-3);
+            assert!((2.0 * offset.y + size.y - avail.y).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn seeded_runs_are_identical() {
+        let params = Params {
+            max_per_cell: 4,
+            collisions: true,
+            quarantine_prob: 0.3,
+            trace_coverage: 0.5,
+            vaccination_rate: 0.1,
+            innate_immune_fraction: 0.1,
+            ..Params::default()
+        };
+        assert_eq!(ensemble::first_divergence(10, 300, &params, 7), None);
+    }
+
+    #[test]
+    fn seed_larger_than_population_is_clamped() {
+        let sim = Pandemic::new(20, 10);
+        assert_eq!(sim.num_infected, 10);
+        assert_eq!(sim.num_healthy, 0);
+    }
+
+    #[test]
+    fn compartments_are_conserved() {
+        let params = Params {
+            infection_prob: 0.8,
+            infection_time_s: 2.0,
+            death_prob: 0.3,
+            max_per_cell: 3,
+            quarantine_prob: 0.5,
+            trace_coverage: 0.5,
+            vaccination_rate: 0.2,
+            natural_immunity_days: 2.0,
+            vaccine_immunity_days: 1.0,
+            ..Params::default()
+        };
+        let mut sim = Pandemic::with_params(20, 1000, params);
+        for _ in 0..1000 {
+            sim.advance(DT);
+
+            assert_eq!(
+                sim.num_healthy + sim.num_infected + sim.num_recovered,
+                sim.total - sim.num_dead
+            );
+            // The counters agree with a recount of the grid
+            let people: Vec<&Person> = sim.grid.0.values().flatten().collect();
+            let count = |state: fn(&InfectionState) -> bool| {
+                people.iter().filter(|person| state(&person.state)).count()
+            };
+            assert_eq!(
+                count(|state| *state == InfectionState::Healthy),
+                sim.num_healthy
+            );
+            assert_eq!(
+                count(|state| matches!(state, InfectionState::Infected(_))),
+                sim.num_infected
+            );
+            assert_eq!(
+                count(|state| *state == InfectionState::Recovered),
+                sim.num_recovered
+            );
+            assert_eq!(
+                people
+                    .iter()
+                    .filter(|person| person.vaccine.is_some())
+                    .count(),
+                sim.num_vaccinated
+            );
+        }
+    }
+
+    #[test]
+    fn field_population_is_conserved() {
+        let params = Params {
+            mode: SimulationMode::Field,
+            seed_day: 1.0,
+            death_prob: 0.3,
+            field_diffusion: 2.0,
+            ..Params::default()
+        };
+        let population = params.field_population as f64;
+        let mut sim = Pandemic::with_params(20, 1000, params);
+        assert!(sim.awaiting_seed);
+        assert_eq!(sim.num_infected, 0);
+        for _ in 0..1000 {
+            sim.advance(DT);
+
+            let totals = sim.field.as_ref().expect("field mode").totals();
+            let everyone = totals.healthy + totals.infected + totals.recovered + totals.dead;
+            assert!((everyone - population).abs() < 1e-6 * population);
+        }
+        // Seeded on day 1, into a growing outbreak
+        assert!(!sim.awaiting_seed);
+        assert!(sim.num_cumulative_infected > 0);
+
+        // Introduced by hand before the seed day
+        let params = Params {
+            mode: SimulationMode::Field,
+            seed_day: 100.0,
+            ..Params::default()
+        };
+        let mut sim = Pandemic::with_params(20, 1000, params);
+        sim.introduce_infection();
+        assert!(!sim.awaiting_seed);
+        assert!(sim.num_infected > 0);
+        assert_eq!(sim.num_cumulative_infected, sim.num_infected);
+        for _ in 0..100 {
+            sim.advance(DT);
+        }
+        let totals = sim.field.as_ref().expect("field mode").totals();
+        let everyone = totals.healthy + totals.infected + totals.recovered + totals.dead;
+        assert!((everyone - population).abs() < 1e-6 * population);
+        assert!(sim.num_cumulative_infected > 0);
+    }
+
+    #[test]
+    fn first_step_after_resume_is_short() {
+        let mut sim = Pandemic::new(5, 300);
+        sim.reset();
+        // Time sitting paused after the reset must not reach the simulation
+        std::thread::sleep(Duration::from_millis(200));
+        sim.resume();
+        sim.step();
+        assert!(sim.time_elapsed < Duration::from_millis(50));
+    }
+}
@@ -0,0 +1,130 @@
+//! Well-mixed (mean-field) SIRD model, for comparison against the spatial simulation
+
+use crate::{PandemicSnapshot, Params, X_MAX_FLOAT, Y_MAX_FLOAT};
+
+/// Integration step, in days
+const DT: f64 = 0.05;
+
+#[derive(Clone, Copy)]
+pub struct Compartments {
+    pub healthy: f64,
+    pub infected: f64,
+    pub recovered: f64,
+    pub dead: f64,
+}
+
+impl Compartments {
+    pub fn add_scaled(self, other: Self, k: f64) -> Self {
+        Self {
+            healthy: self.healthy + other.healthy * k,
+            infected: self.infected + other.infected * k,
+            recovered: self.recovered + other.recovered * k,
+            dead: self.dead + other.dead * k,
+        }
+    }
+
+    /// Rate of change of each compartment
+    pub fn derivative(self, rates: &Rates) -> Self {
+        let living = self.healthy + self.infected + self.recovered;
+        let infections = if living > 0.0 {
+            rates.transmission * self.healthy * self.infected / living
+        } else {
+            0.0
+        };
+        Self {
+            healthy: -infections,
+            infected: infections - (rates.recovery + rates.death) * self.infected,
+            recovered: rates.recovery * self.infected,
+            dead: rates.death * self.infected,
+        }
+    }
+}
+
+impl From<&PandemicSnapshot> for Compartments {
+    fn from(stat: &PandemicSnapshot) -> Self {
+        Self {
+            healthy: stat.num_healthy as f64,
+            infected: stat.num_infected as f64,
+            recovered: stat.num_recovered as f64,
+            dead: stat.num_dead as f64,
+        }
+    }
+}
+
+/// Per-day transition rates equivalent to the spatial model's parameters
+pub struct Rates {
+    /// Contacts that transmit, per infected per day, in a fully susceptible population
+    pub transmission: f64,
+    pub recovery: f64,
+    pub death: f64,
+}
+
+impl Rates {
+    pub fn new(params: &Params, population: usize) -> Self {
+        // Hazard of infection per day while sharing a cell with an infected person
+        let p = (params.infection_prob as f64).min(1.0 - 1e-9);
+        let contact_hazard = -(-p).ln_1p() * params.exposures_per_day() as f64;
+        // Chance that a given other person is in the same cell, were everyone uniformly mixed
+        let cell_share =
+            (params.cell_size as f64).powi(2) / (X_MAX_FLOAT as f64 * Y_MAX_FLOAT as f64);
+
+        // Every infection ends after `infection_time_s`, with `death_prob` of them in death
+        let removal = 1.0 / (params.infection_time_s as f64).max(1e-3);
+        Self {
+            transmission: contact_hazard * cell_share * population as f64,
+            recovery: removal * (1.0 - params.death_prob as f64),
+            death: removal * params.death_prob as f64,
+        }
+    }
+
+    /// Basic reproduction number: secondary infections per case in a susceptible population
+    pub fn r0(&self) -> f64 {
+        self.transmission / (self.recovery + self.death)
+    }
+}
+
+/// Transmission rate that best explains the new infections across `recent` snapshots
+///
+/// Divides the infections over the window by the exposure `S·I/N` integrated across it.
+pub fn fit_transmission(recent: &[PandemicSnapshot]) -> Option<f64> {
+    let (first, last) = (recent.first()?, recent.last()?);
+    let exposure: f64 = recent
+        .windows(2)
+        .map(|pair| {
+            let state = Compartments::from(&pair[0]);
+            let living = state.healthy + state.infected + state.recovered;
+            let days = (pair[1].time - pair[0].time).as_secs_f64();
+            if living > 0.0 {
+                state.healthy * state.infected / living * days
+            } else {
+                0.0
+            }
+        })
+        .sum();
+
+    let infections = (last.cumulative_infected - first.cumulative_infected) as f64;
+    (exposure > 0.0).then(|| infections / exposure)
+}
+
+/// Integrates the model with RK4 from `initial` up to `until_days`, returning `(day, state)`
+/// samples
+pub fn solve(initial: Compartments, rates: &Rates, until_days: f64) -> Vec<(f64, Compartments)> {
+    let mut state = initial;
+    let mut day = 0.0;
+    let mut solution = vec![(day, state)];
+
+    while day < until_days {
+        let k1 = state.derivative(rates);
+        let k2 = state.add_scaled(k1, DT / 2.0).derivative(rates);
+        let k3 = state.add_scaled(k2, DT / 2.0).derivative(rates);
+        let k4 = state.add_scaled(k3, DT).derivative(rates);
+        state = state
+            .add_scaled(k1, DT / 6.0)
+            .add_scaled(k2, DT / 3.0)
+            .add_scaled(k3, DT / 3.0)
+            .add_scaled(k4, DT / 6.0);
+        day += DT;
+        solution.push((day, state));
+    }
+    solution
+}
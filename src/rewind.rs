@@ -0,0 +1,103 @@
+//! Periodic full copies of the simulation state, so a run can be stepped back in time
+
+use crate::{
+    HeatMap, InfectionFront, Milestone, Pandemic, SpatialGrid, export::LineList, field::Field,
+};
+use egui::{Pos2, ahash::HashMap};
+use web_time::{Duration, Instant};
+
+/// Everything `Pandemic::advance` changes, as of one moment
+pub struct Checkpoint {
+    time: Duration,
+    grid: SpatialGrid,
+    field: Option<Field>,
+    heat: HeatMap,
+    contamination: HashMap<(i32, i32), [f32; 2]>,
+    infection_counts: HashMap<(i32, i32), u32>,
+    contacts: HashMap<usize, HashMap<usize, Duration>>,
+    num_healthy: usize,
+    num_infected: usize,
+    num_recovered: usize,
+    num_dead: usize,
+    num_vaccinated: usize,
+    num_cumulative_infected: usize,
+    num_reinfections: usize,
+    line_list: LineList,
+    awaiting_seed: bool,
+    origins: Vec<Pos2>,
+    herd_immunity_at: Option<Duration>,
+    peak_infected: usize,
+    /// Milestones already passed, so those after the checkpoint ping again when reached anew
+    milestones: Vec<(Milestone, Instant)>,
+    front: InfectionFront,
+    max_generation: u32,
+}
+
+impl Checkpoint {
+    pub fn capture(sim: &Pandemic) -> Self {
+        Self {
+            time: sim.time_elapsed,
+            grid: sim.grid.clone(),
+            field: sim.field.clone(),
+            heat: sim.heat.clone(),
+            contamination: sim.contamination.clone(),
+            infection_counts: sim.infection_counts.clone(),
+            contacts: sim.contacts.clone(),
+            num_healthy: sim.num_healthy,
+            num_infected: sim.num_infected,
+            num_recovered: sim.num_recovered,
+            num_dead: sim.num_dead,
+            num_vaccinated: sim.num_vaccinated,
+            num_cumulative_infected: sim.num_cumulative_infected,
+            num_reinfections: sim.num_reinfections,
+            line_list: sim.line_list.clone(),
+            awaiting_seed: sim.awaiting_seed,
+            origins: sim.origins.clone(),
+            herd_immunity_at: sim.herd_immunity_at,
+            peak_infected: sim.peak_infected,
+            milestones: sim.milestones.clone(),
+            front: sim.front.clone(),
+            max_generation: sim.max_generation,
+        }
+    }
+
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+
+    /// Puts `sim` back into this checkpoint's state, dropping stats recorded after it
+    pub fn restore(&self, sim: &mut Pandemic) {
+        sim.time_elapsed = self.time;
+        sim.grid = self.grid.clone();
+        sim.field = self.field.clone();
+        sim.heat = self.heat.clone();
+        sim.contamination = self.contamination.clone();
+        sim.infection_counts = self.infection_counts.clone();
+        sim.contacts = self.contacts.clone();
+        sim.num_healthy = self.num_healthy;
+        sim.num_infected = self.num_infected;
+        sim.num_recovered = self.num_recovered;
+        sim.num_dead = self.num_dead;
+        sim.num_vaccinated = self.num_vaccinated;
+        sim.num_cumulative_infected = self.num_cumulative_infected;
+        sim.num_reinfections = self.num_reinfections;
+        sim.line_list = self.line_list.clone();
+        sim.awaiting_seed = self.awaiting_seed;
+        sim.origins = self.origins.clone();
+        sim.herd_immunity_at = self.herd_immunity_at;
+        sim.peak_infected = self.peak_infected;
+        sim.milestones = self.milestones.clone();
+        sim.front = self.front.clone();
+        sim.max_generation = self.max_generation;
+
+        let kept = sim.stats.partition_point(|stat| stat.time <= self.time);
+        sim.stats.truncate(kept);
+        sim.trajectories.truncate_after(self.time);
+        sim.filmstrip.truncate_after(self.time);
+        sim.first_death = sim.first_death.filter(|&(_, time)| time <= self.time);
+        sim.reported.retain(|&(time, _)| time <= self.time);
+        // Undo scheduled changes the restored time hasn't reached yet
+        sim.revert_schedule();
+        sim.apply_schedule(Duration::ZERO, self.time);
+    }
+}
@@ -0,0 +1,73 @@
+//! The simulation's source of randomness, which can be seeded so runs replay exactly
+//!
+//! Drop-in replacements for `rand::random_bool` and `rand::random_range`, plus normal draws,
+//! from a per-thread generator instead of `rand`'s.
+
+use rand::{
+    Rng, SeedableRng,
+    distr::uniform::{SampleRange, SampleUniform},
+    rngs::StdRng,
+};
+use std::cell::RefCell;
+
+thread_local! {
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::from_os_rng());
+    /// Kept apart for draws that only affect what is shown, so they never shift the
+    /// simulation's sequence
+    static OBSERVATION_RNG: RefCell<StdRng> = RefCell::new(StdRng::from_os_rng());
+}
+
+/// Restarts the generators from `seed`, or from fresh entropy if `None`
+pub fn reseed(seed: Option<u64>) {
+    let from = |seed: Option<u64>| seed.map_or_else(StdRng::from_os_rng, StdRng::seed_from_u64);
+    RNG.set(from(seed));
+    OBSERVATION_RNG.set(from(seed.map(|seed| !seed)));
+}
+
+/// Runs `f` on a generator started from `seed`, then restores the previous generator, so side
+/// runs don't disturb the sequence of the main one
+pub fn with_seed<T>(seed: u64, f: impl FnOnce() -> T) -> T {
+    /// Puts the previous generator back however `f` exits, panics included
+    struct Restore(Option<StdRng>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            if let Some(previous) = self.0.take() {
+                RNG.set(previous);
+            }
+        }
+    }
+
+    let _restore = Restore(Some(RNG.replace(StdRng::seed_from_u64(seed))));
+    f()
+}
+
+/// Runs `f` on a generator of its own, started from fresh entropy rather than the current
+/// generator, so side runs made on the simulation's thread leave its sequence untouched
+pub fn aside<T>(f: impl FnOnce() -> T) -> T {
+    with_seed(rand::random(), f)
+}
+
+/// A standard normal draw from `rng`
+pub fn standard_normal(rng: &mut impl Rng) -> f32 {
+    // Box-Muller transform of two uniform samples
+    let (u1, u2): (f32, f32) = (rng.random_range(f32::EPSILON..1.0), rng.random());
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f32::consts::PI * u2).cos()
+}
+
+/// A standard normal draw from the observation generator
+pub fn observation_noise() -> f32 {
+    OBSERVATION_RNG.with_borrow_mut(standard_normal)
+}
+
+/// A standard normal draw from the simulation's generator
+pub fn random_normal() -> f32 {
+    RNG.with_borrow_mut(standard_normal)
+}
+
+pub fn random_bool(p: f64) -> bool {
+    RNG.with_borrow_mut(|rng| rng.random_bool(p))
+}
+
+pub fn random_range<T: SampleUniform, R: SampleRange<T>>(range: R) -> T {
+    RNG.with_borrow_mut(|rng| rng.random_range(range))
+}
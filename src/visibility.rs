@@ -0,0 +1,51 @@
+//! Page visibility tracking, so a hidden browser tab doesn't advance the simulation clock
+//!
+//! Natively the app is never considered hidden.
+
+#[cfg(target_arch = "wasm32")]
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+#[cfg(target_arch = "wasm32")]
+static HIDDEN: AtomicBool = AtomicBool::new(false);
+#[cfg(target_arch = "wasm32")]
+static CHANGES: AtomicUsize = AtomicUsize::new(0);
+
+/// Whether the page is currently hidden
+#[cfg(target_arch = "wasm32")]
+pub fn hidden() -> bool {
+    HIDDEN.load(Ordering::Relaxed)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn hidden() -> bool {
+    false
+}
+
+/// Number of times the page has been hidden or shown since startup
+#[cfg(target_arch = "wasm32")]
+pub fn changes() -> usize {
+    CHANGES.load(Ordering::Relaxed)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn changes() -> usize {
+    0
+}
+
+/// Listens for `visibilitychange` on `document`, repainting `ctx` so the app notices promptly
+#[cfg(target_arch = "wasm32")]
+pub fn watch(document: web_sys::Document, ctx: egui::Context) {
+    use eframe::wasm_bindgen::{JsCast as _, closure::Closure};
+
+    let target = document.clone();
+    let on_change = Closure::<dyn Fn()>::new(move || {
+        HIDDEN.store(document.hidden(), Ordering::Relaxed);
+        CHANGES.fetch_add(1, Ordering::Relaxed);
+        ctx.request_repaint();
+    });
+    target
+        .add_event_listener_with_callback("visibilitychange", on_change.as_ref().unchecked_ref())
+        .expect("Failed to listen for visibility changes");
+    // The listener lives as long as the page
+    on_change.forget();
+}